@@ -1,8 +1,6 @@
-use fyrox_ui_sdl3::FyroxUiSdl;
-use sdl3::{event::Event, gpu::*, pixels};
+//! Widget gallery shown by the demo, also rendered by the `demo_snapshots` tests.
 
 use fyrox_ui::Thickness;
-use fyrox_ui::button::ButtonMessage;
 use fyrox_ui::menu::MenuItemContent;
 use fyrox_ui::style::StyledProperty;
 use fyrox_ui::tab_control::TabDefinition;
@@ -221,65 +219,3 @@ impl DemoUi {
         .build(&mut ctx);
     }
 }
-
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // initialize SDL and its video subsystem
-    let sdl = sdl3::init().unwrap();
-    let video_subsystem = sdl.video().unwrap();
-
-    // create a new window
-    let window = video_subsystem
-        .window("Hello fyrox UI!", 1280, 720)
-        .position_centered()
-        .resizable()
-        .build()
-        .unwrap();
-
-    let device = Device::new(ShaderFormat::SPIRV, true)
-        .unwrap()
-        .with_window(&window)
-        .unwrap();
-
-    // create platform and renderer
-    let mut fyrox_ui = FyroxUiSdl::new(&device, &window);
-    DemoUi::build(&mut fyrox_ui.ui);
-
-    // start main loop
-    let mut event_pump = sdl.event_pump().unwrap();
-
-    'main: loop {
-        for event in event_pump.poll_iter() {
-            // pass all events to imgui platform
-            fyrox_ui.handle_event(&event, |message| {
-                if let Some(ButtonMessage::Click) = message.data::<ButtonMessage>() {
-                    println!("Button {:?} clicked!", message.destination());
-                }
-            });
-
-            if let Event::Quit { .. } = event {
-                break 'main;
-            }
-        }
-
-        fyrox_ui.update(1.0 / 60.0);
-
-        let mut command_buffer = device.acquire_command_buffer()?;
-
-        if let Ok(swapchain) = command_buffer.wait_and_acquire_swapchain_texture(&window) {
-            let color_targets = [ColorTargetInfo::default()
-                .with_texture(&swapchain)
-                .with_load_op(LoadOp::CLEAR)
-                .with_store_op(StoreOp::STORE)
-                .with_clear_color(pixels::Color::RGB(128, 128, 128))];
-
-            let _ = fyrox_ui.render(&device, &window, &mut command_buffer, &color_targets);
-
-            command_buffer.submit()?;
-        } else {
-            println!("Swapchain unavailable, cancel work");
-            command_buffer.cancel();
-        }
-    }
-
-    Ok(())
-}
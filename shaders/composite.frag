@@ -0,0 +1,10 @@
+#version 450
+#pragma shader_stage(fragment)
+#pragma optimize(on)
+
+layout(location = 0) out vec4 fColor;
+
+// Resolved UI layer, the same size as the target.
+layout(set = 2, binding = 0) uniform sampler2D sLayer;
+
+void main() { fColor = texelFetch(sLayer, ivec2(gl_FragCoord.xy), 0); }
@@ -0,0 +1,11 @@
+#version 450
+#pragma shader_stage(vertex)
+#pragma optimize(on)
+
+out gl_PerVertex { vec4 gl_Position; };
+
+// A single triangle covering the whole viewport.
+void main() {
+  vec2 position = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+  gl_Position = vec4(position * 2.0 - 1.0, 0.0, 1.0);
+}
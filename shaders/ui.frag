@@ -2,13 +2,29 @@
 #pragma shader_stage(fragment)
 #pragma optimize(on)
 
+#define BRUSH_SOLID 0
+#define BRUSH_LINEAR_GRADIENT 1
+#define BRUSH_RADIAL_GRADIENT 2
+#define MAX_GRADIENT_POINTS 16
+
 struct WidgetData {
   vec4 solidColor;
+  vec4 gradientColors[MAX_GRADIENT_POINTS];
+  // std140 pads scalar arrays to 16 bytes per element, so stops are packed by four.
+  vec4 gradientStops[MAX_GRADIENT_POINTS / 4];
+  vec2 gradientOrigin;
+  vec2 gradientEnd;
   vec2 resolution;
   vec2 boundsMin;
   vec2 boundsMax;
   bool isFontTexture;
   float opacity;
+  int brushType;
+  int gradientPointCount;
+  bool premultiply;
+  bool linearOutput;
+  float outputScale;
+  bool linearTexture;
 };
 
 layout(location = 0) out vec4 fColor;
@@ -19,18 +35,84 @@ layout(set = 3, binding = 0) uniform FyroxData { WidgetData widget; };
 layout(location = 0) in vec2 UV;
 layout(location = 1) in vec4 Color;
 
+// Decodes sRGB encoded colors, like the ones of fyrox, to linear values.
+vec3 srgbToLinear(vec3 c) {
+  return mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), greaterThan(c, vec3(0.04045)));
+}
+
+// Encodes linear values, like the ones sampled from sRGB textures, to sRGB.
+vec3 linearToSrgb(vec3 c) {
+  return mix(c * 12.92, 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055, greaterThan(c, vec3(0.0031308)));
+}
+
+float gradientStop(int i) { return widget.gradientStops[i / 4][i % 4]; }
+
+// Projects `p` on the segment `a`-`b` and returns the normalized position along it.
+float projectPoint(vec2 a, vec2 b, vec2 p) {
+  vec2 ab = b - a;
+  return clamp(dot(p - a, ab) / max(dot(ab, ab), 1e-6), 0.0, 1.0);
+}
+
+vec4 brushColor() {
+  if (widget.brushType == BRUSH_SOLID || widget.gradientPointCount == 0) {
+    return widget.solidColor;
+  }
+
+  // Gradient points are given in the local [0; 1] space of the command bounds. SDL GPU puts the
+  // origin of the framebuffer at the top-left corner, like fyrox screen space.
+  vec2 size = max(widget.boundsMax - widget.boundsMin, vec2(1e-6));
+  vec2 localPosition = (gl_FragCoord.xy - widget.boundsMin) / size;
+
+  float t;
+  if (widget.brushType == BRUSH_LINEAR_GRADIENT) {
+    t = projectPoint(widget.gradientOrigin, widget.gradientEnd, localPosition);
+  } else {
+    t = clamp(length(localPosition - widget.gradientOrigin), 0.0, 1.0);
+  }
+
+  int last = widget.gradientPointCount - 1;
+  int current = 0;
+  for (int i = 0; i < widget.gradientPointCount; ++i) {
+    if (t > gradientStop(i)) {
+      current = i;
+    }
+  }
+  int next = min(current + 1, last);
+
+  float delta = gradientStop(next) - gradientStop(current);
+  float mixFactor = delta > 0.0 ? clamp((t - gradientStop(current)) / delta, 0.0, 1.0) : 0.0;
+  return mix(widget.gradientColors[current], widget.gradientColors[next], mixFactor);
+}
+
 void main() {
-  vec4 color = widget.solidColor;
+  // Fyrox colors are sRGB encoded, modulated with the vertex color (selection, etc.).
+  vec4 color = brushColor() * Color;
+  if (widget.linearOutput) {
+    // Decoding the whole color keeps the look of gamma space rendering, only blending differs.
+    color.rgb = srgbToLinear(color.rgb);
+  }
 
   if (widget.isFontTexture) {
     // Font atlas is single channel (A8), use alpha
-    float alpha = texture(sTexture, UV.st).a;
-    color = vec4(color.rgb, color.a * alpha);
+    color.a *= texture(sTexture, UV.st).a;
   } else {
-    // Regular textured elements
-    color *= texture(sTexture, UV.st);
+    // Regular textured elements, brought to the encoding of the output.
+    vec4 texel = texture(sTexture, UV.st);
+    if (widget.linearOutput && !widget.linearTexture) {
+      texel.rgb = srgbToLinear(texel.rgb);
+    } else if (!widget.linearOutput && widget.linearTexture) {
+      texel.rgb = linearToSrgb(texel.rgb);
+    }
+    color *= texel;
   }
 
   color.a *= widget.opacity;
-  fColor = color * Color; // modulate with vertex color (selection, etc.)
+  if (widget.linearOutput) {
+    color.rgb *= widget.outputScale;
+  }
+  if (widget.premultiply) {
+    color.rgb *= color.a;
+  }
+
+  fColor = color;
 }
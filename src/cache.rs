@@ -0,0 +1,223 @@
+//! Bookkeeping of the resources the renderer backends upload for the UI.
+
+use fyrox_ui::font::{FontHeight, FontResource};
+use fyrox_ui::texture::{Texture, TextureKind, TextureResource};
+use std::collections::HashMap;
+
+/// Identifies a single atlas page of a font.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct FontPageKey {
+    // `FontHeight` is a float, so we key on its bit pattern.
+    height: u32,
+    page_index: usize,
+}
+
+impl FontPageKey {
+    fn new(height: &FontHeight, page_index: usize) -> Self {
+        Self {
+            height: height.0.to_bits(),
+            page_index,
+        }
+    }
+}
+
+/// Uploaded atlas pages of fonts, keyed by font resource.
+pub(crate) type FontPageCache<V> = ResourceCache<FontResource, HashMap<FontPageKey, V>>;
+
+/// Uploaded copies of user textures, keyed by texture resource.
+pub(crate) type TextureCache<V> = ResourceCache<TextureResource, UploadedTexture<V>>;
+
+/// Copy of a texture resource, along with the version of the data it was made from.
+pub(crate) struct UploadedTexture<V> {
+    value: V,
+    modifications_count: u64,
+}
+
+/// Fyrox resource a cache entry is made from.
+pub(crate) trait CachedResource: Clone {
+    fn key(&self) -> u64;
+    fn use_count(&self) -> usize;
+}
+
+impl CachedResource for FontResource {
+    fn key(&self) -> u64 {
+        FontResource::key(self)
+    }
+
+    fn use_count(&self) -> usize {
+        FontResource::use_count(self)
+    }
+}
+
+impl CachedResource for TextureResource {
+    fn key(&self) -> u64 {
+        TextureResource::key(self)
+    }
+
+    fn use_count(&self) -> usize {
+        TextureResource::use_count(self)
+    }
+}
+
+/// Values made from fyrox resources, e.g. uploaded textures, keyed by their resource.
+///
+/// Each entry holds a clone of its resource, so a use count of 1 means nobody but the cache
+/// refers to it anymore, and [`ResourceCache::evict_unused`] drops the entry.
+pub(crate) struct ResourceCache<R, V> {
+    entries: HashMap<u64, CacheEntry<R, V>>,
+}
+
+struct CacheEntry<R, V> {
+    resource: R,
+    value: V,
+}
+
+impl<R: CachedResource, V> ResourceCache<R, V> {
+    pub(crate) fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn get(&self, resource: &R) -> Option<&V> {
+        self.entries.get(&resource.key()).map(|entry| &entry.value)
+    }
+
+    pub(crate) fn get_mut(&mut self, resource: &R) -> Option<&mut V> {
+        self.entries.get_mut(&resource.key()).map(|entry| &mut entry.value)
+    }
+
+    pub(crate) fn contains(&self, resource: &R) -> bool {
+        self.entries.contains_key(&resource.key())
+    }
+
+    /// Return the value of `resource`, inserting the one made by `f` if there is none.
+    pub(crate) fn get_or_insert_with(&mut self, resource: &R, f: impl FnOnce() -> V) -> &mut V {
+        &mut self
+            .entries
+            .entry(resource.key())
+            .or_insert_with(|| CacheEntry {
+                resource: resource.clone(),
+                value: f(),
+            })
+            .value
+    }
+
+    pub(crate) fn insert(&mut self, resource: &R, value: V) {
+        self.entries.insert(
+            resource.key(),
+            CacheEntry {
+                resource: resource.clone(),
+                value,
+            },
+        );
+    }
+
+    pub(crate) fn remove(&mut self, resource: &R) -> Option<V> {
+        self.entries.remove(&resource.key()).map(|entry| entry.value)
+    }
+
+    /// Drop the entries of resources that nobody else refers to.
+    pub(crate) fn evict_unused(&mut self) {
+        self.entries.retain(|_, entry| entry.resource.use_count() > 1);
+    }
+}
+
+impl<V> FontPageCache<V> {
+    /// Upload of the atlas page `page_index` of `font` for `height`, if any.
+    pub(crate) fn page(&self, font: &FontResource, height: &FontHeight, page_index: usize) -> Option<&V> {
+        self.get(font)?.get(&FontPageKey::new(height, page_index))
+    }
+
+    /// Upload the atlas page `page_index` of `font` for `height` with `upload`, if it is new or
+    /// was modified since the last upload.
+    ///
+    /// `upload` is given the coverage of the page, one byte per pixel, the size of the page and
+    /// the previous upload of the page, if any, to update in place.
+    pub(crate) fn update_page<E>(
+        &mut self,
+        font: &FontResource,
+        height: &FontHeight,
+        page_index: usize,
+        upload: impl FnOnce(&[u8], u32, Option<V>) -> Result<V, E>,
+    ) -> Result<(), E> {
+        let pages = self.get_or_insert_with(font, HashMap::new);
+
+        let mut state = font.state();
+        let Some(font) = state.data() else {
+            return Ok(());
+        };
+
+        let page_size = font.page_size() as u32;
+        let Some(page) = font
+            .atlases
+            .get_mut(height)
+            .and_then(|atlas| atlas.pages.get_mut(page_index))
+        else {
+            return Ok(());
+        };
+
+        let key = FontPageKey::new(height, page_index);
+        if pages.contains_key(&key) && !page.modified {
+            return Ok(());
+        }
+
+        let value = upload(&page.pixels, page_size, pages.remove(&key))?;
+        pages.insert(key, value);
+        page.modified = false;
+
+        Ok(())
+    }
+}
+
+impl<V> TextureCache<V> {
+    /// Upload of `resource`, if any.
+    pub(crate) fn texture(&self, resource: &TextureResource) -> Option<&V> {
+        self.get(resource).map(|uploaded| &uploaded.value)
+    }
+
+    /// Upload `resource` with `upload`, if it is new or was modified since the last upload.
+    ///
+    /// `upload` is given the texture data, its width and height, and the previous upload, if any,
+    /// to reuse when the layout of the data did not change. It returns `None` for data it cannot
+    /// upload, which is then drawn untextured. Textures still loading, render targets, which have
+    /// no pixels on the CPU side, and textures other than rectangles are skipped.
+    pub(crate) fn update<E>(
+        &mut self,
+        resource: &TextureResource,
+        upload: impl FnOnce(&Texture, u32, u32, Option<V>) -> Result<Option<V>, E>,
+    ) -> Result<(), E> {
+        let state = resource.state();
+        let Some(data) = state.data_ref() else {
+            return Ok(());
+        };
+        if data.is_render_target() {
+            return Ok(());
+        }
+
+        let modifications_count = data.modifications_count();
+        if self
+            .get(resource)
+            .is_some_and(|uploaded| uploaded.modifications_count == modifications_count)
+        {
+            return Ok(());
+        }
+
+        let TextureKind::Rectangle { width, height } = data.kind() else {
+            return Ok(());
+        };
+
+        let previous = self.remove(resource).map(|uploaded| uploaded.value);
+        if let Some(value) = upload(data, width, height, previous)? {
+            self.insert(
+                resource,
+                UploadedTexture {
+                    value,
+                    modifications_count,
+                },
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,373 @@
+//! Fallback renderer backend that draws Fyrox-UI `DrawingContext` through SDL's 2D `Renderer`.
+//!
+//! It needs no GPU API nor shaders, so it also runs on SDL's software renderer. Compared to
+//! [`UiRenderer`](crate::renderer::UiRenderer), gradients are evaluated per vertex instead of per
+//! pixel, clipping geometry is ignored in favor of the clip bounds rectangle, and only the first
+//! mip level of textures is used.
+//!
+//! The backend is only available with the `canvas` feature and is not selected at runtime:
+//! [`FyroxUiSdl`](crate::FyroxUiSdl) always uses the GPU renderer, so apps drawing through a
+//! canvas pair a [`CanvasRenderer`] with a [`Platform`](crate::platform::Platform) themselves.
+
+use crate::cache::{FontPageCache, ResourceCache, TextureCache};
+use crate::error::Error;
+use crate::platform::WindowScale;
+use crate::renderer::{RenderStats, batches};
+use crate::utils::{brush_color, color_to_array, rgba_pixels};
+use fyrox_ui::draw::{CommandTexture, DrawingContext};
+use fyrox_ui::font::{FontHeight, FontResource};
+use fyrox_ui::texture::{TexturePixelKind, TextureResource};
+use sdl3::pixels::{FColor, PixelFormat, PixelFormatEnum};
+use sdl3::rect::Rect;
+use sdl3::render::{BlendMode, Canvas, FPoint, Texture, TextureCreator, Vertex, VertexIndices};
+use sdl3::video::{Window, WindowContext};
+use std::borrow::Cow;
+
+/// SDL format with the byte order of RGBA pixels in memory.
+#[cfg(target_endian = "little")]
+const RGBA32: PixelFormatEnum = PixelFormatEnum::ABGR8888;
+#[cfg(target_endian = "big")]
+const RGBA32: PixelFormatEnum = PixelFormatEnum::RGBA8888;
+
+/// SDL format with the byte order of BGRA pixels in memory.
+#[cfg(target_endian = "little")]
+const BGRA32: PixelFormatEnum = PixelFormatEnum::ARGB8888;
+#[cfg(target_endian = "big")]
+const BGRA32: PixelFormatEnum = PixelFormatEnum::BGRA8888;
+
+/// Canvas copy of a user texture resource.
+struct TextureCacheEntry<'a> {
+    texture: Texture<'a>,
+    // Layout of the uploaded data, a change requires a new texture.
+    width: u32,
+    height: u32,
+    format: PixelFormatEnum,
+}
+
+/// Renderer resources for drawing the UI on an SDL `Canvas`.
+pub struct CanvasRenderer<'a> {
+    texture_creator: &'a TextureCreator<WindowContext>,
+    // Uploaded font atlas pages, keyed by font resource.
+    font_pages: FontPageCache<Texture<'a>>,
+    // Uploaded user textures, keyed by texture resource.
+    textures: TextureCache<TextureCacheEntry<'a>>,
+    // Scratch storage for the vertices of one draw call.
+    vertices: Vec<Vertex>,
+    stats: RenderStats,
+}
+
+impl<'a> CanvasRenderer<'a> {
+    /// Create a renderer whose textures are created with `texture_creator`, which must belong to
+    /// the canvas passed to [`CanvasRenderer::render`].
+    pub fn new(texture_creator: &'a TextureCreator<WindowContext>) -> Self {
+        Self {
+            texture_creator,
+            font_pages: ResourceCache::new(),
+            textures: ResourceCache::new(),
+            vertices: Vec::new(),
+            stats: RenderStats::default(),
+        }
+    }
+
+    /// Statistics of the last rendered frame.
+    pub fn stats(&self) -> RenderStats {
+        self.stats
+    }
+
+    /// Render Fyrox-UI `DrawingContext` on `canvas`.
+    ///
+    /// Nothing is cleared nor presented, so the UI is drawn over whatever the canvas already holds.
+    pub fn render(&mut self, canvas: &mut Canvas<Window>, drawing: &DrawingContext) -> Result<(), Error> {
+        let scale = WindowScale::of(canvas.window());
+        self.stats = RenderStats::default();
+
+        self.update_textures(drawing)?;
+
+        let mut stats = RenderStats {
+            commands: drawing.get_commands().len(),
+            draw_calls: 0,
+        };
+        for (cmd_ui, triangles) in batches(drawing.get_commands()) {
+            // Clip rectangle from clip bounds, in framebuffer pixels.
+            let mut clip = cmd_ui.clip_bounds;
+            clip.position *= scale.display_scale;
+            clip.size *= scale.display_scale;
+            let sc_w = clip.size.x.ceil().max(0.0) as u32;
+            let sc_h = clip.size.y.ceil().max(0.0) as u32;
+            if sc_w == 0 || sc_h == 0 {
+                continue;
+            }
+            canvas.set_clip_rect(Rect::new(
+                clip.position.x.floor() as i32,
+                clip.position.y.floor() as i32,
+                sc_w,
+                sc_h,
+            ));
+
+            // There are no shaders here, so the brush and opacity are baked into vertex colors.
+            self.vertices.clear();
+            for triangle in &drawing.get_triangles()[triangles] {
+                for &index in triangle.indices() {
+                    let vertex = &drawing.get_vertices()[index as usize];
+                    let brush = brush_color(&cmd_ui.brush, &cmd_ui.bounds, vertex.pos);
+                    let color = color_to_array(vertex.color);
+                    self.vertices.push(Vertex {
+                        position: FPoint::new(vertex.pos.x * scale.display_scale, vertex.pos.y * scale.display_scale),
+                        color: FColor::RGBA(
+                            brush[0] * color[0],
+                            brush[1] * color[1],
+                            brush[2] * color[2],
+                            brush[3] * color[3] * cmd_ui.opacity,
+                        ),
+                        tex_coord: FPoint::new(vertex.tex_coord.x, vertex.tex_coord.y),
+                    });
+                }
+            }
+
+            let texture = match &cmd_ui.texture {
+                CommandTexture::Font {
+                    font,
+                    page_index,
+                    height,
+                } => self.font_pages.page(font, height, *page_index),
+                CommandTexture::Texture(resource) => self.textures.texture(resource).map(|entry| &entry.texture),
+                CommandTexture::None => None,
+            };
+
+            canvas
+                .render_geometry(&self.vertices, texture, VertexIndices::Sequential)
+                .map_err(Error::CanvasDraw)?;
+            stats.draw_calls += 1;
+        }
+        self.stats = stats;
+
+        canvas.set_clip_rect(None);
+
+        Ok(())
+    }
+
+    /// Upload the font atlas pages and user textures referenced by `drawing` that are new or were
+    /// modified since the last upload, and drop the ones that are no longer used by the UI.
+    fn update_textures(&mut self, drawing: &DrawingContext) -> Result<(), Error> {
+        self.font_pages.evict_unused();
+        self.textures.evict_unused();
+
+        for cmd_ui in drawing.get_commands() {
+            match &cmd_ui.texture {
+                CommandTexture::Font {
+                    font,
+                    page_index,
+                    height,
+                } => self.update_font_page(font, height, *page_index)?,
+                CommandTexture::Texture(texture) => self.update_texture(texture)?,
+                CommandTexture::None => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn update_font_page(&mut self, font: &FontResource, height: &FontHeight, page_index: usize) -> Result<(), Error> {
+        let texture_creator = self.texture_creator;
+        self.font_pages
+            .update_page(font, height, page_index, |coverage, page_size, cached| {
+                let mut texture = match cached {
+                    Some(texture) => texture,
+                    None => create_texture(texture_creator, RGBA32, page_size, page_size)?,
+                };
+                // The 2D API has no alpha-only format, so glyphs become white with the coverage as
+                // alpha.
+                let pixels: Vec<u8> = coverage.iter().flat_map(|&alpha| [255, 255, 255, alpha]).collect();
+                texture
+                    .update(None, &pixels, page_size as usize * 4)
+                    .map_err(|err| Error::CanvasTexture(err.into()))?;
+                Ok(texture)
+            })
+    }
+
+    fn update_texture(&mut self, resource: &TextureResource) -> Result<(), Error> {
+        let texture_creator = self.texture_creator;
+        self.textures.update(resource, |data, width, height, cached| {
+            let Some((format, pixels)) = canvas_pixels(data.pixel_kind(), data.mip_level_data(0)) else {
+                return Ok(None);
+            };
+
+            // Reuse the texture unless the layout of the data changed.
+            let mut texture = match cached {
+                Some(entry) if entry.width == width && entry.height == height && entry.format == format => {
+                    entry.texture
+                }
+                _ => create_texture(texture_creator, format, width, height)?,
+            };
+            texture
+                .update(None, &pixels, width as usize * 4)
+                .map_err(|err| Error::CanvasTexture(err.into()))?;
+
+            Ok(Some(TextureCacheEntry {
+                texture,
+                width,
+                height,
+                format,
+            }))
+        })
+    }
+}
+
+/// Create a streaming texture blended like the GPU pipeline.
+fn create_texture(
+    texture_creator: &TextureCreator<WindowContext>,
+    format: PixelFormatEnum,
+    width: u32,
+    height: u32,
+) -> Result<Texture<'_>, Error> {
+    let mut texture = texture_creator
+        .create_texture_streaming(PixelFormat::from(format), width, height)
+        .map_err(|err| Error::CanvasTexture(err.into()))?;
+    texture.set_blend_mode(BlendMode::Blend);
+    Ok(texture)
+}
+
+/// Convert fyrox pixels to a 4 bytes per pixel layout SDL renderers support.
+fn canvas_pixels(pixel_kind: TexturePixelKind, data: &[u8]) -> Option<(PixelFormatEnum, Cow<'_, [u8]>)> {
+    match pixel_kind {
+        TexturePixelKind::BGRA8 => Some((BGRA32, Cow::Borrowed(data))),
+        _ => rgba_pixels(pixel_kind, data).map(|pixels| (RGBA32, pixels)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::SDL_TEST_LOCK;
+    use fyrox_ui::brush::Brush;
+    use fyrox_ui::core::color::Color;
+    use fyrox_ui::core::math::Rect as UiRect;
+    use fyrox_ui::draw::Draw;
+    use fyrox_ui::style::DEFAULT_STYLE;
+    use fyrox_ui::texture::{TextureKind, TextureResourceExtension};
+    use sdl3::{Sdl, VideoSubsystem};
+    use std::sync::{MutexGuard, PoisonError};
+
+    /// Canvas of SDL's software renderer on a hidden window of the offscreen video driver, so the
+    /// tests need neither a display nor a GPU.
+    struct SoftwareCanvas {
+        canvas: Canvas<Window>,
+        _video: VideoSubsystem,
+        _sdl: Sdl,
+        _lock: MutexGuard<'static, ()>,
+    }
+
+    impl SoftwareCanvas {
+        fn new(width: u32, height: u32) -> Self {
+            let lock = SDL_TEST_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+            sdl3::hint::set("SDL_VIDEO_DRIVER", "offscreen");
+            let sdl = sdl3::init().unwrap();
+            let video = sdl.video().unwrap();
+            let window = video.window("canvas test", width, height).hidden().build().unwrap();
+            let mut canvas = sdl3::render::create_renderer(window, Some(c"software")).unwrap();
+            canvas.set_draw_color(sdl3::pixels::Color::RGB(0, 0, 0));
+            canvas.clear();
+
+            Self {
+                canvas,
+                _video: video,
+                _sdl: sdl,
+                _lock: lock,
+            }
+        }
+
+        /// Render `drawing` with a new renderer and read the canvas back, as RGB rows from the
+        /// top-left corner.
+        fn render(&mut self, drawing: &DrawingContext) -> Vec<[u8; 3]> {
+            let texture_creator = self.canvas.texture_creator();
+            let mut renderer = CanvasRenderer::new(&texture_creator);
+            renderer.render(&mut self.canvas, drawing).unwrap();
+
+            let surface = self
+                .canvas
+                .read_pixels(None)
+                .unwrap()
+                .convert_format(PixelFormat::from(RGBA32))
+                .unwrap();
+            let (width, height, pitch) = (
+                surface.width() as usize,
+                surface.height() as usize,
+                surface.pitch() as usize,
+            );
+            surface.with_lock(|pixels| {
+                (0..height)
+                    .flat_map(|y| (0..width).map(move |x| y * pitch + x * 4))
+                    .map(|offset| [pixels[offset], pixels[offset + 1], pixels[offset + 2]])
+                    .collect()
+            })
+        }
+    }
+
+    /// A 4×4 rectangle drawn with `brush` and `texture`, clipped to `clip_bounds`.
+    fn rect(clip_bounds: UiRect<f32>, brush: Brush, texture: CommandTexture) -> DrawingContext {
+        let mut drawing = DrawingContext::new(DEFAULT_STYLE.resource.clone());
+        drawing.push_rect_filled(&UiRect::new(0.0, 0.0, 4.0, 4.0), None);
+        drawing.commit(clip_bounds, brush, texture, None);
+        drawing
+    }
+
+    #[test]
+    fn solid_brushes_fill_their_geometry() {
+        let mut canvas = SoftwareCanvas::new(4, 4);
+        let drawing = rect(
+            UiRect::new(0.0, 0.0, 4.0, 4.0),
+            Brush::Solid(Color::opaque(255, 128, 0)),
+            CommandTexture::None,
+        );
+
+        assert_eq!(canvas.render(&drawing), vec![[255, 128, 0]; 16]);
+    }
+
+    #[test]
+    fn textures_are_mapped_on_their_geometry() {
+        // Left half red, right half blue.
+        let texels = [[255, 0, 0, 255], [255, 0, 0, 255], [0, 0, 255, 255], [0, 0, 255, 255]];
+        let texture = TextureResource::from_bytes(
+            TextureKind::Rectangle { width: 4, height: 4 },
+            TexturePixelKind::RGBA8,
+            [texels; 4].as_flattened().as_flattened().to_vec(),
+            Default::default(),
+        )
+        .unwrap();
+
+        let mut canvas = SoftwareCanvas::new(4, 4);
+        let drawing = rect(
+            UiRect::new(0.0, 0.0, 4.0, 4.0),
+            Brush::Solid(Color::WHITE),
+            CommandTexture::Texture(texture),
+        );
+        let pixels = canvas.render(&drawing);
+
+        // Middle columns may be filtered across the halves, the outer ones cannot.
+        for row in pixels.chunks_exact(4) {
+            assert_eq!(row[0], [255, 0, 0], "{row:?}");
+            assert_eq!(row[3], [0, 0, 255], "{row:?}");
+        }
+    }
+
+    #[test]
+    fn clip_bounds_restrict_drawing() {
+        let mut canvas = SoftwareCanvas::new(4, 4);
+        let drawing = rect(
+            UiRect::new(1.0, 1.0, 2.0, 2.0),
+            Brush::Solid(Color::GREEN),
+            CommandTexture::None,
+        );
+
+        let green = [0, 255, 0];
+        let none = [0, 0, 0];
+        #[rustfmt::skip]
+        let expected = vec![
+            none, none, none, none,
+            none, green, green, none,
+            none, green, green, none,
+            none, none, none, none,
+        ];
+        assert_eq!(canvas.render(&drawing), expected);
+    }
+}
@@ -0,0 +1,157 @@
+//! Error type of the crate.
+
+use sdl3::gpu::{SwapchainComposition, TextureFormat};
+use std::fmt;
+#[cfg(feature = "snapshot")]
+use std::path::PathBuf;
+
+/// Errors reported by [`UiRenderer`](crate::renderer::UiRenderer) and
+/// [`FyroxUiSdl`](crate::FyroxUiSdl).
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// A UI shader could not be created.
+    Shader(sdl3::Error),
+    /// A UI graphics pipeline could not be created.
+    Pipeline(sdl3::Error),
+    /// A sampler could not be created.
+    Sampler(sdl3::Error),
+    /// Creating or uploading textures or geometry failed.
+    Upload(sdl3::Error),
+    /// A command buffer or pass could not be acquired, begun or submitted.
+    Command(sdl3::Error),
+    /// Downloading a texture to the CPU failed.
+    Readback(sdl3::Error),
+    /// Textures of this format cannot be downloaded.
+    ReadbackFormat(TextureFormat),
+    /// A texture of the canvas backend could not be created or updated.
+    #[cfg(feature = "canvas")]
+    CanvasTexture(Box<dyn std::error::Error + Send + Sync>),
+    /// Drawing through the canvas backend failed.
+    #[cfg(feature = "canvas")]
+    CanvasDraw(sdl3::Error),
+    /// SDL or the GPU device of [`Headless`](crate::snapshot::Headless) could not be initialized.
+    #[cfg(feature = "snapshot")]
+    Headless(sdl3::Error),
+    /// A snapshot image could not be read or written.
+    #[cfg(feature = "snapshot")]
+    SnapshotFile {
+        path: PathBuf,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    /// The render and the reference snapshot have different sizes.
+    #[cfg(feature = "snapshot")]
+    SnapshotSize {
+        path: PathBuf,
+        expected: (u32, u32),
+        found: (u32, u32),
+    },
+    /// The reference snapshot does not exist, the render was written to `actual`.
+    #[cfg(feature = "snapshot")]
+    SnapshotMissing { path: PathBuf, actual: PathBuf },
+    /// Some pixels of the render differ from the reference snapshot beyond the tolerance.
+    #[cfg(feature = "snapshot")]
+    SnapshotMismatch {
+        path: PathBuf,
+        pixels: usize,
+        diff: PathBuf,
+    },
+    /// The renderer cannot encode its output for this swapchain composition.
+    UnsupportedComposition(SwapchainComposition),
+    /// Multisampled rendering needs a single color target to composite the resolved UI over.
+    MultisampleTarget,
+    /// The pipelines of the current blend mode have no composite pipeline, which multisampled
+    /// rendering needs, as they were built for a single sample.
+    CompositePipeline,
+    /// A depth-stencil target is required because stencil clipping is enabled, or was given
+    /// although it is not.
+    DepthStencilTarget { required: bool },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Shader(err) => write!(f, "failed to create UI shader: {err}"),
+            Error::Pipeline(err) => write!(f, "failed to create UI pipeline: {err}"),
+            Error::Sampler(err) => write!(f, "failed to create UI sampler: {err}"),
+            Error::Upload(err) => write!(f, "failed to upload UI data: {err}"),
+            Error::Command(err) => write!(f, "failed to record UI commands: {err}"),
+            Error::Readback(err) => write!(f, "failed to download texture: {err}"),
+            Error::ReadbackFormat(format) => write!(f, "cannot download textures of format {format:?}"),
+            #[cfg(feature = "canvas")]
+            Error::CanvasTexture(err) => write!(f, "failed to upload UI texture to the canvas: {err}"),
+            #[cfg(feature = "canvas")]
+            Error::CanvasDraw(err) => write!(f, "failed to draw UI on the canvas: {err}"),
+            #[cfg(feature = "snapshot")]
+            Error::Headless(err) => write!(f, "failed to create a headless GPU device: {err}"),
+            #[cfg(feature = "snapshot")]
+            Error::SnapshotMissing { path, actual } => write!(
+                f,
+                "snapshot {} does not exist, see {} and set UPDATE_SNAPSHOTS to record it",
+                path.display(),
+                actual.display()
+            ),
+            #[cfg(feature = "snapshot")]
+            Error::SnapshotFile { path, source } => write!(f, "failed to access snapshot {}: {source}", path.display()),
+            #[cfg(feature = "snapshot")]
+            Error::SnapshotSize { path, expected, found } => write!(
+                f,
+                "snapshot {} is {}x{} but the render is {}x{}",
+                path.display(),
+                expected.0,
+                expected.1,
+                found.0,
+                found.1
+            ),
+            #[cfg(feature = "snapshot")]
+            Error::SnapshotMismatch { path, pixels, diff } => write!(
+                f,
+                "{pixels} pixels differ from snapshot {}, see {}",
+                path.display(),
+                diff.display()
+            ),
+            Error::UnsupportedComposition(composition) => {
+                write!(f, "UI rendering to a {composition:?} swapchain is not supported")
+            }
+            Error::MultisampleTarget => write!(f, "multisampled UI rendering needs a single color target"),
+            Error::CompositePipeline => write!(f, "no pipeline to composite the multisampled UI with"),
+            Error::DepthStencilTarget { required: true } => {
+                write!(f, "stencil clipping is enabled but no depth-stencil target was given")
+            }
+            Error::DepthStencilTarget { required: false } => {
+                write!(
+                    f,
+                    "a depth-stencil target was given but stencil clipping is not enabled"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Shader(err)
+            | Error::Pipeline(err)
+            | Error::Sampler(err)
+            | Error::Upload(err)
+            | Error::Command(err)
+            | Error::Readback(err) => Some(err),
+            #[cfg(feature = "canvas")]
+            Error::CanvasTexture(err) => Some(err.as_ref()),
+            #[cfg(feature = "canvas")]
+            Error::CanvasDraw(err) => Some(err),
+            #[cfg(feature = "snapshot")]
+            Error::Headless(err) => Some(err),
+            #[cfg(feature = "snapshot")]
+            Error::SnapshotFile { source, .. } => Some(source.as_ref()),
+            #[cfg(feature = "snapshot")]
+            Error::SnapshotMissing { .. } | Error::SnapshotSize { .. } | Error::SnapshotMismatch { .. } => None,
+            Error::ReadbackFormat(_)
+            | Error::UnsupportedComposition(_)
+            | Error::MultisampleTarget
+            | Error::CompositePipeline
+            | Error::DepthStencilTarget { .. } => None,
+        }
+    }
+}
@@ -1,12 +1,27 @@
+mod cache;
+#[cfg(feature = "canvas")]
+pub mod canvas;
+pub mod error;
 pub mod platform;
+pub mod readback;
 pub mod renderer;
+#[cfg(feature = "snapshot")]
+pub mod snapshot;
+pub mod software;
+#[cfg(test)]
+mod test_support;
 pub mod utils;
 
-use crate::platform::Platform;
-use crate::renderer::UiRenderer;
+pub use crate::error::Error;
+
+use crate::platform::{Platform, WindowScale};
+use crate::readback::{Readback, ReadbackTarget, submit_with_readback};
+use crate::renderer::{PreparedFrame, UiRenderer, UiRendererConfig};
 use fyrox_ui::UiUpdateSwitches;
+use fyrox_ui::widget::WidgetMessage;
 use fyrox_ui::{UserInterface, core::algebra::Vector2, message::UiMessage};
 use sdl3::gpu::*;
+use sdl3::sys::gpu::{SDL_BlitGPUTexture, SDL_GPUBlitInfo, SDL_GPUBlitRegion, SDL_GPUFilter, SDL_GPUTexture};
 
 pub struct FyroxUiSdl {
     pub ui: UserInterface,
@@ -15,22 +30,78 @@ pub struct FyroxUiSdl {
 
     pub width: f32,
     pub height: f32,
+
+    // Frame rendered by `render_with_readback`, with its format.
+    capture: Option<ReadbackTarget>,
 }
 
 impl FyroxUiSdl {
+    /// Create the UI and its renderer for `window`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the renderer cannot be created, see [`FyroxUiSdl::try_new`].
     pub fn new(device: &sdl3::gpu::Device, window: &sdl3::video::Window) -> Self {
-        let ui = UserInterface::new(Vector2::new(window.size().0 as f32, window.size().1 as f32));
+        Self::try_new(device, window).expect("failed to create fyrox UI")
+    }
+
+    /// Create the UI and its renderer for `window`, reporting failures instead of panicking.
+    pub fn try_new(device: &sdl3::gpu::Device, window: &sdl3::video::Window) -> Result<Self, Error> {
+        Self::with_config(device, window, UiRendererConfig::default())
+    }
+
+    /// Create the UI with custom renderer options, see [`UiRendererConfig`].
+    pub fn with_config(
+        device: &sdl3::gpu::Device,
+        window: &sdl3::video::Window,
+        config: UiRendererConfig,
+    ) -> Result<Self, Error> {
+        let scale = WindowScale::of(window);
+        let size = scale.ui_size();
+        let ui = UserInterface::new(size);
 
-        let renderer = UiRenderer::new(device, window);
-        let platform = Platform::new();
+        let renderer = UiRenderer::with_config(device, window, config)?;
+        let mut platform = Platform::new();
+        platform.set_window(window);
 
-        Self {
+        Ok(Self {
             ui,
             renderer,
             platform,
-            width: window.size().0 as f32,
-            height: window.size().1 as f32,
-        }
+            width: size.x,
+            height: size.y,
+            capture: None,
+        })
+    }
+
+    /// Create the UI and its renderer for `width`×`height` pixel targets of the given format,
+    /// independent of any window, e.g. to render it offscreen with
+    /// [`FyroxUiSdl::render_to_target`].
+    pub fn with_format(
+        device: &sdl3::gpu::Device,
+        format: TextureFormat,
+        (width, height): (u32, u32),
+        config: UiRendererConfig,
+    ) -> Result<Self, Error> {
+        let scale = WindowScale {
+            size_in_pixels: (width, height),
+            ..WindowScale::default()
+        };
+        let size = scale.ui_size();
+        let ui = UserInterface::new(size);
+
+        let renderer = UiRenderer::with_format(device, format, config)?;
+        let mut platform = Platform::new();
+        platform.set_window_scale(scale);
+
+        Ok(Self {
+            ui,
+            renderer,
+            platform,
+            width: size.x,
+            height: size.y,
+            capture: None,
+        })
     }
 
     pub fn handle_event<T>(&mut self, event: &sdl3::event::Event, mut event_callback: T)
@@ -39,12 +110,22 @@ impl FyroxUiSdl {
     {
         self.platform.handle_event(&mut self.ui, event);
 
+        // Window size and scale events resize the UI.
+        let size = self.ui.screen_size();
+        self.width = size.x;
+        self.height = size.y;
+
         while let Some(message) = self.ui.poll_message() {
+            self.platform.handle_message(&mut self.ui, &message);
             event_callback(message);
         }
     }
 
-    /// Resize UI when window size changes.
+    /// Resize the UI, in UI units.
+    ///
+    /// Window size and display scale changes are already applied by `handle_event`, this is only
+    /// needed to lay the UI out at a custom size, or when window events are disabled with
+    /// [`Platform::set_window_events`].
     pub fn resize(&mut self, width: f32, height: f32) {
         self.width = width;
         self.height = height;
@@ -52,28 +133,273 @@ impl FyroxUiSdl {
     }
 
     /// Pump a frame of UI logic (animations, layout, message routing).
+    ///
+    /// The OS cursor is then set to the icon of the hovered widget, see [`Platform::set_cursor`],
+    /// and text input follows the focused text box, see [`Platform::update_text_input`].
     pub fn update(&mut self, dt: f32) {
         self.ui.update(
             Vector2::new(self.width, self.height),
             dt,
             &UiUpdateSwitches { node_overrides: None },
         );
+        self.platform.set_cursor(self.ui.cursor());
+        self.platform.update_text_input(&self.ui);
     }
 
     /// Send a generic UI message (you usually route input events to this).
+    ///
+    /// The pre-edit text of the input method is removed before focus messages, see
+    /// [`Platform::cancel_composition`].
     pub fn send_message(&mut self, message: UiMessage) {
+        if matches!(
+            message.data::<WidgetMessage>(),
+            Some(WidgetMessage::Focus | WidgetMessage::Unfocus)
+        ) {
+            self.platform.cancel_composition(&mut self.ui);
+        }
         self.ui.send_message(message);
     }
 
+    /// Draw the UI and render it into `color_targets`, see [`UiRenderer::render`].
+    ///
+    /// Nothing is rendered while the window is minimized, see [`Platform::is_minimized`].
     pub fn render(
         &mut self,
         device: &sdl3::gpu::Device,
         window: &sdl3::video::Window,
         command_buffer: &mut CommandBuffer,
         color_targets: &[ColorTargetInfo],
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let draw_ctx = self.ui.draw();
-        self.renderer
-            .render(device, window, command_buffer, color_targets, draw_ctx)
+        depth_stencil_target: Option<&DepthStencilTargetInfo>,
+    ) -> Result<(), Error> {
+        if self.platform.is_minimized() {
+            return Ok(());
+        }
+
+        self.ui.draw();
+        self.renderer.locate_draw_callbacks(&self.ui);
+        let draw_ctx = self.ui.get_drawing_context();
+        self.renderer.render(
+            device,
+            window,
+            command_buffer,
+            color_targets,
+            depth_stencil_target,
+            draw_ctx,
+        )
+    }
+
+    /// Draw the UI and render it into `color_targets` of `size` pixels, independent of any window,
+    /// see [`UiRenderer::render_to_target`].
+    pub fn render_to_target(
+        &mut self,
+        device: &sdl3::gpu::Device,
+        command_buffer: &mut CommandBuffer,
+        color_targets: &[ColorTargetInfo],
+        depth_stencil_target: Option<&DepthStencilTargetInfo>,
+        size: (u32, u32),
+    ) -> Result<(), Error> {
+        self.ui.draw();
+        self.renderer.locate_draw_callbacks(&self.ui);
+        let draw_ctx = self.ui.get_drawing_context();
+        self.renderer.render_to_target(
+            device,
+            command_buffer,
+            color_targets,
+            depth_stencil_target,
+            size,
+            draw_ctx,
+        )
+    }
+
+    /// Draw the UI over `clear_color`, show it on the swapchain of `window` and download the frame,
+    /// e.g. for screenshots.
+    ///
+    /// Swapchain textures cannot be downloaded, see [`readback`], so the frame is rendered into a
+    /// texture of the renderer, which is downloaded and blitted to the swapchain. This acquires the
+    /// swapchain texture and submits `command_buffer`, in place of `command_buffer.submit()`. Passes
+    /// recorded before only show up on screen, the returned [`Readback`] holds the UI frame.
+    /// `depth_stencil_target` is handled as in [`UiRenderer::render`].
+    pub fn render_with_readback(
+        &mut self,
+        device: &sdl3::gpu::Device,
+        window: &sdl3::video::Window,
+        mut command_buffer: CommandBuffer,
+        clear_color: sdl3::pixels::Color,
+        depth_stencil_target: Option<&DepthStencilTargetInfo>,
+    ) -> Result<Readback, Error> {
+        // Nothing can fail once the swapchain is acquired, as SDL cannot cancel the command buffer
+        // anymore, so the frame is rendered first.
+        let capture = match self.capture_texture(device, window) {
+            Ok(capture) => capture,
+            Err(err) => {
+                command_buffer.cancel();
+                return Err(err);
+            }
+        };
+        let rendered = {
+            let color_targets = [ColorTargetInfo::default()
+                .with_texture(capture.texture())
+                .with_load_op(LoadOp::CLEAR)
+                .with_store_op(StoreOp::STORE)
+                .with_clear_color(clear_color)];
+            // The UI pass is skipped when there is nothing to draw, so the clear gets its own.
+            device
+                .begin_render_pass(&command_buffer, &color_targets, None)
+                .map_err(Error::Command)
+                .and_then(|clear_pass| {
+                    device.end_render_pass(clear_pass);
+
+                    let color_targets = [ColorTargetInfo::default()
+                        .with_texture(capture.texture())
+                        .with_load_op(LoadOp::LOAD)
+                        .with_store_op(StoreOp::STORE)];
+                    self.render(
+                        device,
+                        window,
+                        &mut command_buffer,
+                        &color_targets,
+                        depth_stencil_target,
+                    )
+                })
+        };
+        if let Err(err) = rendered {
+            command_buffer.cancel();
+            return Err(err);
+        }
+
+        // Only the raw texture is kept, the wrapper borrows the command buffer.
+        let swapchain = match command_buffer.wait_and_acquire_swapchain_texture(window) {
+            Ok(swapchain) => (swapchain.raw(), swapchain.width(), swapchain.height()),
+            Err(err) => {
+                command_buffer.cancel();
+                return Err(Error::Command(err));
+            }
+        };
+        // There is no swapchain texture to show the frame on while the window is minimized.
+        if !swapchain.0.is_null() {
+            blit(&command_buffer, capture.texture(), swapchain);
+        }
+
+        submit_with_readback(device, command_buffer, &capture.source())
+    }
+
+    /// Texture `render_with_readback` renders into, recreated when the size or format of the
+    /// swapchain of `window` changed.
+    fn capture_texture(
+        &mut self,
+        device: &sdl3::gpu::Device,
+        window: &sdl3::video::Window,
+    ) -> Result<ReadbackTarget, Error> {
+        // The renderer follows the swapchain format, the capture follows the renderer.
+        let format = device.get_swapchain_texture_format(window);
+        if format != self.renderer.format() {
+            self.renderer.set_format(device, format)?;
+        }
+
+        let (width, height) = WindowScale::of(window).size_in_pixels;
+        match &self.capture {
+            Some(capture)
+                if capture.format() == format
+                    && capture.texture().width() == width
+                    && capture.texture().height() == height =>
+            {
+                Ok(capture.clone())
+            }
+            _ => {
+                let capture = self.renderer.create_readback_target(device, width, height)?;
+                self.capture = Some(capture.clone());
+                Ok(capture)
+            }
+        }
+    }
+
+    /// Draw the UI and upload its textures and geometry, to record it into a render pass of your
+    /// own with [`PreparedFrame::draw`].
+    ///
+    /// Must be called while no render pass is open, see [`UiRenderer::prepare`].
+    pub fn prepare(
+        &mut self,
+        device: &sdl3::gpu::Device,
+        command_buffer: &CommandBuffer,
+    ) -> Result<PreparedFrame<'_>, Error> {
+        self.ui.draw();
+        self.renderer.locate_draw_callbacks(&self.ui);
+        let draw_ctx = self.ui.get_drawing_context();
+        self.renderer.prepare(device, command_buffer, draw_ctx)
+    }
+}
+
+/// Copy the whole `source` texture onto the `(texture, width, height)` swapchain texture, scaled to
+/// its size.
+fn blit(command_buffer: &CommandBuffer, source: &Texture, (swapchain, width, height): (*mut SDL_GPUTexture, u32, u32)) {
+    let info = SDL_GPUBlitInfo {
+        source: SDL_GPUBlitRegion {
+            texture: source.raw(),
+            w: source.width(),
+            h: source.height(),
+            ..Default::default()
+        },
+        destination: SDL_GPUBlitRegion {
+            texture: swapchain,
+            w: width,
+            h: height,
+            ..Default::default()
+        },
+        load_op: LoadOp::DONT_CARE,
+        filter: SDL_GPUFilter::LINEAR,
+        ..Default::default()
+    };
+    unsafe { SDL_BlitGPUTexture(command_buffer.raw(), &info) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestGpu;
+    use fyrox_ui::border::BorderBuilder;
+    use fyrox_ui::brush::Brush;
+    use fyrox_ui::core::color::Color;
+    use fyrox_ui::widget::WidgetBuilder;
+    use fyrox_ui::{Thickness, style::StyledProperty};
+
+    #[test]
+    #[ignore = "needs a GPU device, run with --ignored"]
+    fn render_with_readback_downloads_the_frame() {
+        let gpu = TestGpu::new();
+        let window = gpu.video.window("readback test", 8, 8).build().unwrap();
+        let device = gpu.device.clone().with_window(&window).unwrap();
+
+        let mut fyrox_ui = FyroxUiSdl::try_new(&device, &window).unwrap();
+        let ctx = &mut fyrox_ui.ui.build_ctx();
+        BorderBuilder::new(
+            WidgetBuilder::new()
+                .with_width(4.0)
+                .with_height(8.0)
+                .with_background(StyledProperty::from(Brush::Solid(Color::RED))),
+        )
+        .with_stroke_thickness(StyledProperty::from(Thickness::uniform(0.0)))
+        .build(ctx);
+        fyrox_ui.update(1.0 / 60.0);
+
+        let command_buffer = device.acquire_command_buffer().unwrap();
+        let screenshot = fyrox_ui
+            .render_with_readback(
+                &device,
+                &window,
+                command_buffer,
+                sdl3::pixels::Color::RGB(0, 0, 255),
+                None,
+            )
+            .unwrap()
+            .wait()
+            .unwrap();
+
+        assert_eq!((screenshot.width, screenshot.height), (8, 8));
+        // The border covers the left half, the clear color shows on the right one.
+        let pixel = |x: usize, y: usize| &screenshot.pixels[(y * 8 + x) * 4..][..4];
+        for y in 0..8 {
+            assert_eq!(pixel(1, y), [255, 0, 0, 255], "left of row {y}");
+            assert_eq!(pixel(6, y), [0, 0, 255, 255], "right of row {y}");
+        }
     }
 }
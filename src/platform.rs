@@ -1,41 +1,558 @@
 //! SDL3 -> Fyrox UI adapter: feed SDL events into Fyrox UI.
 
-use fyrox_ui::UserInterface;
-use fyrox_ui::core::algebra::Vector2;
-use fyrox_ui::message::{ButtonState, KeyCode, KeyboardModifiers, MouseButton, OsEvent};
+use fyrox_ui::core::algebra::{Point2, Vector2};
+use fyrox_ui::core::pool::Handle;
+use fyrox_ui::message::{
+    ButtonState, CursorIcon, KeyCode, KeyboardModifiers, MessageDirection, MouseButton, OsEvent, UiMessage,
+};
+use fyrox_ui::text_box::TextBox;
+use fyrox_ui::widget::WidgetMessage;
+use fyrox_ui::{UiNode, UserInterface};
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
 
 use sdl3::{
-    event::Event,
-    keyboard::{Mod, Scancode},
-    mouse::MouseButton as SdlMouseButton,
+    event::{Event, WindowEvent},
+    keyboard::{Keycode, Mod, Scancode},
+    mouse::{Cursor, MouseButton as SdlMouseButton, SystemCursor},
+    sys::events::SDL_EVENT_WINDOW_DISPLAY_SCALE_CHANGED,
+    sys::keyboard::{
+        SDL_GetKeyboardFocus, SDL_GetModState, SDL_SetTextInputArea, SDL_StartTextInput, SDL_StopTextInput,
+    },
+    sys::keycode::SDL_KMOD_NONE,
+    sys::mouse::{SDL_HideCursor, SDL_ShowCursor},
+    sys::rect::SDL_Rect,
+    sys::video::{
+        SDL_GetWindowDisplayScale, SDL_GetWindowFromID, SDL_GetWindowID, SDL_GetWindowPixelDensity,
+        SDL_GetWindowSizeInPixels, SDL_Window,
+    },
     video::Window,
 };
 
+/// Scale factors of a window, relating SDL window coordinates, framebuffer pixels and fyrox UI
+/// units.
+///
+/// The UI is laid out in units of `display_scale` pixels, so it keeps its physical size on high
+/// density displays while the framebuffer is rendered at full resolution.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WindowScale {
+    /// Size of the window framebuffer in pixels.
+    pub size_in_pixels: (u32, u32),
+    /// Framebuffer pixels per window coordinate, as used by SDL event positions.
+    pub pixel_density: f32,
+    /// Framebuffer pixels per UI unit.
+    pub display_scale: f32,
+}
+
+impl WindowScale {
+    /// Query the current scale of `window`.
+    pub fn of(window: &Window) -> Self {
+        unsafe { Self::from_raw(window.raw()) }
+    }
+
+    /// Query the current scale of the window with the given SDL id, if it still exists.
+    pub fn from_window_id(window_id: u32) -> Option<Self> {
+        let window = unsafe { SDL_GetWindowFromID(window_id) };
+        (!window.is_null()).then(|| unsafe { Self::from_raw(window) })
+    }
+
+    /// # Safety
+    ///
+    /// `window` must be a valid SDL window.
+    unsafe fn from_raw(window: *mut SDL_Window) -> Self {
+        let (mut width, mut height) = (0, 0);
+        let (pixel_density, display_scale) = unsafe {
+            SDL_GetWindowSizeInPixels(window, &mut width, &mut height);
+            (SDL_GetWindowPixelDensity(window), SDL_GetWindowDisplayScale(window))
+        };
+
+        // Both queries return 0.0 on failure, fall back to an unscaled window.
+        let valid = |scale: f32| if scale > 0.0 { scale } else { 1.0 };
+        Self {
+            size_in_pixels: (width.max(0) as u32, height.max(0) as u32),
+            pixel_density: valid(pixel_density),
+            display_scale: valid(display_scale),
+        }
+    }
+
+    /// Size of the window in UI units.
+    pub fn ui_size(&self) -> Vector2<f32> {
+        Vector2::new(
+            self.size_in_pixels.0 as f32 / self.display_scale,
+            self.size_in_pixels.1 as f32 / self.display_scale,
+        )
+    }
+
+    /// Convert a position in SDL window coordinates to UI units.
+    pub fn window_to_ui(&self, x: f32, y: f32) -> Vector2<f32> {
+        Vector2::new(x, y) * (self.pixel_density / self.display_scale)
+    }
+
+    /// Convert a position in UI units to SDL window coordinates.
+    pub fn ui_to_window(&self, position: Vector2<f32>) -> Vector2<f32> {
+        position * (self.display_scale / self.pixel_density)
+    }
+}
+
+impl Default for WindowScale {
+    fn default() -> Self {
+        Self {
+            size_in_pixels: (0, 0),
+            pixel_density: 1.0,
+            display_scale: 1.0,
+        }
+    }
+}
+
+/// How key events are turned into fyrox `KeyCode`s.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum KeyMapping {
+    /// Keys are identified by their physical position, e.g. the key right of Tab is always
+    /// `KeyQ`. Suits game controls like WASD.
+    #[default]
+    Physical,
+    /// Character keys are identified by what the keyboard layout prints on them, e.g. the key
+    /// right of Tab is `KeyA` on AZERTY keyboards, so shortcuts like Ctrl+Z follow their letter.
+    /// Keys without a character, or whose character has no `KeyCode`, fall back to `Physical`.
+    Layout,
+}
+
+/// Pre-edit text of an input method, shown inline in the focused text box until it is committed.
+struct Composition {
+    // Character index of the pre-edit text in the text box.
+    start: usize,
+    // Pre-edit text, checked to still be there before removing it.
+    text: Vec<char>,
+}
+
+/// Cursor position given to the UI when the cursor leaves the window, far from any widget.
+const OUTSIDE_WINDOW: Vector2<f32> = Vector2::new(-1.0e6, -1.0e6);
+
 /// SDL3 backend platform state for Fyrox UI.
-pub struct Platform;
+pub struct Platform {
+    scale: WindowScale,
+    // SDL id of the window the UI is shown in.
+    window_id: Option<u32>,
+    key_mapping: KeyMapping,
+    window_events: bool,
+    minimized: bool,
+    // Keys and mouse buttons the UI saw pressed, released on focus loss.
+    pressed_keys: Vec<KeyCode>,
+    pressed_buttons: Vec<MouseButton>,
+    // Icon of the OS cursor.
+    cursor: CursorIcon,
+    cursor_visible: bool,
+    // System cursors created so far, SDL only shows a cursor while it is alive.
+    cursors: HashMap<SystemCursor, Cursor>,
+    // Editable text box with the keyboard focus, text input is enabled while there is one.
+    text_box: Handle<UiNode>,
+    // Window text input was started on.
+    text_input_window: Option<u32>,
+    // Last input area given to SDL, in window coordinates.
+    text_input_area: Option<(SDL_Rect, i32)>,
+    composition: Option<Composition>,
+}
 
 impl Default for Platform {
     fn default() -> Self {
-        Self::new()
+        Self {
+            scale: WindowScale::default(),
+            window_id: None,
+            key_mapping: KeyMapping::default(),
+            window_events: true,
+            minimized: false,
+            pressed_keys: Vec::new(),
+            pressed_buttons: Vec::new(),
+            // The SDL default cursor is an arrow.
+            cursor: CursorIcon::Default,
+            cursor_visible: true,
+            cursors: HashMap::new(),
+            text_box: Handle::NONE,
+            text_input_window: None,
+            text_input_area: None,
+            composition: None,
+        }
     }
 }
 
 impl Platform {
     /// Create a new platform adapter.
+    ///
+    /// Event coordinates are forwarded unscaled until [`Platform::set_window`] or
+    /// [`Platform::set_window_scale`] is called, or a window size event is handled.
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// Scale used to convert event coordinates to UI units.
+    pub fn window_scale(&self) -> WindowScale {
+        self.scale
+    }
+
+    /// Set the scale used to convert event coordinates to UI units.
+    pub fn set_window_scale(&mut self, scale: WindowScale) {
+        self.scale = scale;
+    }
+
+    /// SDL id of the window the UI is shown in, see [`Platform::set_window`].
+    pub fn window_id(&self) -> Option<u32> {
+        self.window_id
+    }
+
+    /// Show the UI in `window`, taking its current scale.
+    ///
+    /// sdl3 reports display scale changes without the id of their window, so the scale is only
+    /// refreshed on them once the window is known.
+    pub fn set_window(&mut self, window: &Window) {
+        self.window_id = Some(window.id());
+        self.scale = WindowScale::of(window);
+    }
+
+    /// How key events are turned into fyrox key codes.
+    pub fn key_mapping(&self) -> KeyMapping {
+        self.key_mapping
+    }
+
+    /// Set how key events are turned into fyrox key codes, [`KeyMapping::Physical`] by default.
+    pub fn set_key_mapping(&mut self, key_mapping: KeyMapping) {
+        self.key_mapping = key_mapping;
+    }
+
+    /// Whether [`Platform::handle_event`] handles window events, see
+    /// [`Platform::set_window_events`].
+    pub fn window_events(&self) -> bool {
+        self.window_events
+    }
+
+    /// Enable or disable the handling of window events, enabled by default.
+    ///
+    /// When enabled, size and scale changes resize the UI, focus loss releases the keys and mouse
+    /// buttons the UI saw pressed, focus gain resynchronizes the modifiers, the cursor leaving the
+    /// window clears the hovered widget and minimization is tracked. Apps managing this themselves
+    /// can disable it and call [`Platform::release_inputs`] and [`Platform::set_window_scale`].
+    ///
+    /// Once the window is known, see [`Platform::set_window`], events of other windows are ignored.
+    pub fn set_window_events(&mut self, enabled: bool) {
+        self.window_events = enabled;
+    }
+
+    /// Whether the window is minimized, [`FyroxUiSdl::render`](crate::FyroxUiSdl::render) skips
+    /// frames meanwhile.
+    pub fn is_minimized(&self) -> bool {
+        self.minimized
+    }
+
+    /// Release every key and mouse button the UI saw pressed, and clear the modifiers.
+    ///
+    /// Release events are lost when the window is not focused, so keys would otherwise stay
+    /// pressed in the UI.
+    pub fn release_inputs(&mut self, ui: &mut UserInterface) {
+        for button in self.pressed_buttons.drain(..) {
+            ui.process_os_event(&OsEvent::MouseInput {
+                button,
+                state: ButtonState::Released,
+            });
+        }
+        for button in self.pressed_keys.drain(..) {
+            ui.process_os_event(&OsEvent::KeyboardInput {
+                button,
+                state: ButtonState::Released,
+                text: String::new(),
+            });
+        }
+        ui.process_os_event(&OsEvent::KeyboardModifiers(KeyboardModifiers::default()));
+    }
+
+    /// Icon of the OS cursor.
+    pub fn cursor(&self) -> CursorIcon {
+        self.cursor
+    }
+
+    /// Show the SDL system cursor closest to `icon`.
+    ///
+    /// [`FyroxUiSdl::update`](crate::FyroxUiSdl::update) applies the cursor the UI asks for on its
+    /// own. System cursors are created on first use and kept for the next ones. If SDL fails to
+    /// create one, the current cursor stays.
+    pub fn set_cursor(&mut self, icon: CursorIcon) {
+        if icon == self.cursor {
+            return;
+        }
+
+        let system_cursor = map_cursor_icon(icon);
+        let cursor = match self.cursors.entry(system_cursor) {
+            Entry::Occupied(cursor) => cursor.into_mut(),
+            Entry::Vacant(slot) => match Cursor::from_system(system_cursor) {
+                Ok(cursor) => slot.insert(cursor),
+                Err(_) => return,
+            },
+        };
+        cursor.set();
+        self.cursor = icon;
+    }
+
+    /// Whether the OS cursor is shown over the window.
+    pub fn is_cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    /// Show or hide the OS cursor, e.g. while the app draws its own or uses relative mouse mode.
+    ///
+    /// Fyrox cursor icons have no hidden state, so the cursor stays hidden until shown again
+    /// whatever icon the UI asks for.
+    pub fn set_cursor_visible(&mut self, visible: bool) {
+        if visible == self.cursor_visible {
+            return;
+        }
+
+        unsafe {
+            if visible {
+                SDL_ShowCursor();
+            } else {
+                SDL_HideCursor();
+            }
+        }
+        self.cursor_visible = visible;
+    }
+
+    /// Track the keyboard focus of the UI from one of its messages, to enable text input while
+    /// an editable text box is focused.
+    ///
+    /// [`FyroxUiSdl::handle_event`](crate::FyroxUiSdl::handle_event) passes every polled message.
+    pub fn handle_message(&mut self, ui: &mut UserInterface, message: &UiMessage) {
+        if message.direction() != MessageDirection::FromWidget {
+            return;
+        }
+
+        match message.data::<WidgetMessage>() {
+            Some(WidgetMessage::Focus) => {
+                let editable = ui
+                    .nodes()
+                    .try_borrow(message.destination())
+                    .and_then(|node| node.cast::<TextBox>())
+                    .is_some_and(|text_box| *text_box.editable);
+                if editable {
+                    self.cancel_composition(ui);
+                    self.text_box = message.destination();
+                }
+            }
+            Some(WidgetMessage::Unfocus) if message.destination() == self.text_box => {
+                self.cancel_composition(ui);
+                self.text_box = Handle::NONE;
+            }
+            _ => {}
+        }
+    }
+
+    /// Enable SDL text input while an editable text box has the keyboard focus, and place the
+    /// input method candidate window at its caret.
+    ///
+    /// [`FyroxUiSdl::update`](crate::FyroxUiSdl::update) calls it after laying the UI out.
+    pub fn update_text_input(&mut self, ui: &UserInterface) {
+        let text_box = ui
+            .nodes()
+            .try_borrow(self.text_box)
+            .and_then(|node| node.cast::<TextBox>())
+            .filter(|text_box| *text_box.editable);
+        let Some(text_box) = text_box else {
+            self.text_box = Handle::NONE;
+            self.composition = None;
+            if let Some(window_id) = self.text_input_window.take() {
+                let window = unsafe { SDL_GetWindowFromID(window_id) };
+                if !window.is_null() {
+                    unsafe { SDL_StopTextInput(window) };
+                }
+            }
+            self.text_input_area = None;
+            return;
+        };
+
+        let window = match self.text_input_window {
+            Some(window_id) => unsafe { SDL_GetWindowFromID(window_id) },
+            None => {
+                let window = unsafe { SDL_GetKeyboardFocus() };
+                if window.is_null() || !unsafe { SDL_StartTextInput(window) } {
+                    return;
+                }
+                self.text_input_window = Some(unsafe { SDL_GetWindowID(window) });
+                window
+            }
+        };
+        if window.is_null() {
+            self.text_input_window = None;
+            return;
+        }
+
+        // Line of the caret across the text box, with the caret as the cursor offset.
+        let transform = text_box.visual_transform();
+        let caret = text_box.caret_local_position() - *text_box.view_position;
+        let line_height = **text_box.formatted_text.borrow().font_size();
+        let to_window = |local: Vector2<f32>| {
+            let screen = transform.transform_point(&Point2::from(local)).coords;
+            self.scale.ui_to_window(screen)
+        };
+        let bounds = text_box.screen_bounds();
+        let left = self.scale.ui_to_window(bounds.position).x;
+        let right = self.scale.ui_to_window(bounds.right_bottom_corner()).x;
+        let top = to_window(caret);
+        let bottom = to_window(caret + Vector2::new(0.0, line_height));
+        let area = (
+            SDL_Rect {
+                x: left.floor() as i32,
+                y: top.y.floor() as i32,
+                w: (right - left).ceil().max(1.0) as i32,
+                h: (bottom.y - top.y).ceil().max(1.0) as i32,
+            },
+            (top.x - left).round().max(0.0) as i32,
+        );
+
+        let changed = self.text_input_area.is_none_or(|(rect, cursor)| {
+            (rect.x, rect.y, rect.w, rect.h, cursor) != (area.0.x, area.0.y, area.0.w, area.0.h, area.1)
+        });
+        if changed {
+            unsafe { SDL_SetTextInputArea(window, &area.0, area.1) };
+            self.text_input_area = Some(area);
+        }
+    }
+
+    /// Show the pre-edit text of the input method in the focused text box, with the caret
+    /// `cursor` characters into it.
+    fn compose(&mut self, ui: &mut UserInterface, text: &str, cursor: i32) {
+        if text.is_empty() && self.composition.is_none() {
+            return;
+        }
+        let Some(text_box) = ui
+            .try_get_node_mut(self.text_box)
+            .and_then(|node| node.cast_mut::<TextBox>())
+        else {
+            self.composition = None;
+            return;
+        };
+
+        let removed = self
+            .composition
+            .take()
+            .and_then(|composition| remove_composition(text_box, &composition));
+        let start = match removed {
+            Some(start) => start,
+            None => {
+                // The pre-edit text is inserted at the caret, a selection would be replaced on commit.
+                text_box.selection_range.set_value_and_mark_modified(None);
+                let text_len = text_box.formatted_text.borrow().get_raw_text().len();
+                text_box
+                    .position_to_char_index_unclamped(*text_box.caret_position)
+                    .unwrap_or_default()
+                    .min(text_len)
+            }
+        };
+
+        let chars: Vec<char> = text.chars().collect();
+        let len = chars.len();
+        if len > 0 {
+            text_box.formatted_text.borrow_mut().insert_str(text, start).build();
+            self.composition = Some(Composition { start, text: chars });
+        }
+        let cursor = usize::try_from(cursor).map_or(len, |cursor| cursor.min(len));
+        set_caret(text_box, start + cursor);
+    }
+
+    /// Remove the pre-edit text of the input method from the focused text box.
+    ///
+    /// This is done before inserting the committed text in its place, and before any event that
+    /// can move the keyboard focus, so text boxes committing their text on focus loss never see
+    /// the pre-edit text. Apps moving the focus with their own messages should call this first,
+    /// [`FyroxUiSdl::send_message`](crate::FyroxUiSdl::send_message) does for focus messages.
+    pub fn cancel_composition(&mut self, ui: &mut UserInterface) {
+        let Some(composition) = self.composition.take() else {
+            return;
+        };
+        if let Some(text_box) = ui
+            .try_get_node_mut(self.text_box)
+            .and_then(|node| node.cast_mut::<TextBox>())
+            && let Some(start) = remove_composition(text_box, &composition)
+        {
+            set_caret(text_box, start);
+        }
+    }
+
+    fn handle_window_event(&mut self, ui: &mut UserInterface, window_id: u32, win_event: &WindowEvent) -> bool {
+        if !self.window_events || self.window_id.is_some_and(|id| id != window_id) {
+            return false;
+        }
+
+        match win_event {
+            WindowEvent::Resized(..) | WindowEvent::PixelSizeChanged(..) | WindowEvent::DisplayChanged(..) => {
+                return self.refresh_scale(ui, window_id);
+            }
+            WindowEvent::Minimized => self.minimized = true,
+            WindowEvent::Restored | WindowEvent::Maximized => self.minimized = false,
+            WindowEvent::FocusLost => {
+                self.cancel_composition(ui);
+                self.release_inputs(ui);
+            }
+            WindowEvent::FocusGained => {
+                // Modifiers may have been pressed or released while another window had the focus.
+                let keymod = Mod::from_bits_truncate(unsafe { SDL_GetModState() });
+                ui.process_os_event(&OsEvent::KeyboardModifiers(map_modifiers(keymod)));
+            }
+            WindowEvent::MouseLeave => {
+                ui.process_os_event(&OsEvent::CursorMoved {
+                    position: OUTSIDE_WINDOW,
+                });
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// Query the scale of the window `window_id` and resize the UI to it.
+    fn refresh_scale(&mut self, ui: &mut UserInterface, window_id: u32) -> bool {
+        let Some(scale) = WindowScale::from_window_id(window_id) else {
+            return false;
+        };
+        self.scale = scale;
+        ui.set_screen_size(scale.ui_size());
+        true
+    }
+
+    fn map_key(&self, sc: Scancode) -> Option<KeyCode> {
+        match self.key_mapping {
+            KeyMapping::Physical => map_scancode(sc),
+            // The unmodified key is used rather than the one of the event, so a release maps to
+            // the same key as its press even if modifiers changed in between.
+            KeyMapping::Layout => map_layout_key(Keycode::from_scancode(sc, SDL_KMOD_NONE, true), sc),
+        }
     }
 
     /// Handle a single SDL3 event and forward it to Fyrox UI as an `OsEvent`.
+    ///
+    /// Window events are handled as described in [`Platform::set_window_events`].
     pub fn handle_event(&mut self, ui: &mut UserInterface, event: &Event) -> bool {
         match *event {
+            Event::Window {
+                window_id,
+                ref win_event,
+                ..
+            } => self.handle_window_event(ui, window_id, win_event),
+
+            // sdl3 has no variant for display scale changes and reports them as unknown events.
+            Event::Unknown { type_, .. } if type_ == SDL_EVENT_WINDOW_DISPLAY_SCALE_CHANGED.0 => match self.window_id {
+                Some(window_id) if self.window_events => self.refresh_scale(ui, window_id),
+                _ => false,
+            },
+
             Event::MouseWheel { x, y, .. } => {
                 ui.process_os_event(&OsEvent::MouseWheel(x, y));
                 true
             }
 
             Event::MouseButtonDown { mouse_btn, .. } => {
+                // Clicks can move the focus, the text box must lose it without the pre-edit text.
+                self.cancel_composition(ui);
                 if let Some(btn) = map_mouse_button(mouse_btn) {
+                    if !self.pressed_buttons.contains(&btn) {
+                        self.pressed_buttons.push(btn);
+                    }
                     ui.process_os_event(&OsEvent::MouseInput {
                         button: btn,
                         state: ButtonState::Pressed,
@@ -46,6 +563,7 @@ impl Platform {
 
             Event::MouseButtonUp { mouse_btn, .. } => {
                 if let Some(btn) = map_mouse_button(mouse_btn) {
+                    self.pressed_buttons.retain(|&pressed| pressed != btn);
                     ui.process_os_event(&OsEvent::MouseInput {
                         button: btn,
                         state: ButtonState::Released,
@@ -54,7 +572,14 @@ impl Platform {
                 true
             }
 
+            Event::TextEditing { ref text, start, .. } => {
+                self.compose(ui, text, start);
+                true
+            }
+
             Event::TextInput { ref text, .. } => {
+                // The committed text replaces the pre-edit one.
+                self.cancel_composition(ui);
                 // SDL may deliver multiple UTF-8 chars at once; forward each as a separate key text event.
                 for ch in text.chars() {
                     ui.process_os_event(&OsEvent::KeyboardInput {
@@ -73,7 +598,14 @@ impl Platform {
             } => {
                 // Send modifiers separately (Fyrox models them as an independent event)
                 ui.process_os_event(&OsEvent::KeyboardModifiers(map_modifiers(keymod)));
-                if let Some(key) = map_scancode(sc) {
+                // Keys typed while composing edit the pre-edit text, the text box must not see them.
+                if self.composition.is_some() {
+                    return true;
+                }
+                if let Some(key) = self.map_key(sc) {
+                    if !self.pressed_keys.contains(&key) {
+                        self.pressed_keys.push(key);
+                    }
                     ui.process_os_event(&OsEvent::KeyboardInput {
                         button: key,
                         state: ButtonState::Pressed,
@@ -89,7 +621,13 @@ impl Platform {
                 ..
             } => {
                 ui.process_os_event(&OsEvent::KeyboardModifiers(map_modifiers(keymod)));
-                if let Some(key) = map_scancode(sc) {
+                if let Some(key) = self.map_key(sc) {
+                    // Releases of keys the UI never saw pressed, e.g. swallowed while composing or
+                    // pressed before the window had focus, must not reach it either.
+                    if !self.pressed_keys.contains(&key) {
+                        return true;
+                    }
+                    self.pressed_keys.retain(|&pressed| pressed != key);
                     ui.process_os_event(&OsEvent::KeyboardInput {
                         button: key,
                         state: ButtonState::Released,
@@ -101,7 +639,7 @@ impl Platform {
 
             Event::MouseMotion { x, y, .. } => {
                 ui.process_os_event(&OsEvent::CursorMoved {
-                    position: Vector2::new(x, y),
+                    position: self.scale.window_to_ui(x, y),
                 });
                 true
             }
@@ -111,6 +649,25 @@ impl Platform {
     }
 }
 
+/// Remove the pre-edit text of `composition` from `text_box` and return where it was, unless the
+/// text was replaced in the meantime.
+fn remove_composition(text_box: &mut TextBox, composition: &Composition) -> Option<usize> {
+    let range = composition.start..composition.start + composition.text.len();
+    let mut formatted_text = text_box.formatted_text.borrow_mut();
+    if formatted_text.get_raw_text().get(range.clone()) != Some(composition.text.as_slice()) {
+        return None;
+    }
+    formatted_text.remove_range(range).build();
+    Some(composition.start)
+}
+
+/// Move the caret of `text_box` to the character `index`, without selecting.
+fn set_caret(text_box: &mut TextBox, index: usize) {
+    let position = text_box.char_index_to_position(index).unwrap_or_default();
+    text_box.caret_position.set_value_and_mark_modified(position);
+    text_box.invalidate_layout();
+}
+
 /// Returns `true` if the provided event is associated with the provided window.
 pub fn filter_event(window: &Window, event: &Event) -> bool {
     Some(window.id()) == event.get_window_id()
@@ -127,6 +684,35 @@ fn map_mouse_button(btn: SdlMouseButton) -> Option<MouseButton> {
     })
 }
 
+/// Pick the SDL system cursor for a fyrox cursor icon, the closest one when SDL has no match.
+fn map_cursor_icon(icon: CursorIcon) -> SystemCursor {
+    match icon {
+        CursorIcon::Default
+        | CursorIcon::ContextMenu
+        | CursorIcon::Help
+        | CursorIcon::Alias
+        | CursorIcon::Copy
+        | CursorIcon::ZoomIn
+        | CursorIcon::ZoomOut => SystemCursor::Arrow,
+        CursorIcon::Pointer | CursorIcon::Grab | CursorIcon::Grabbing => SystemCursor::Hand,
+        CursorIcon::Progress => SystemCursor::WaitArrow,
+        CursorIcon::Wait => SystemCursor::Wait,
+        CursorIcon::Cell | CursorIcon::Crosshair => SystemCursor::Crosshair,
+        CursorIcon::Text | CursorIcon::VerticalText => SystemCursor::IBeam,
+        CursorIcon::Move | CursorIcon::AllScroll => SystemCursor::SizeAll,
+        CursorIcon::NoDrop | CursorIcon::NotAllowed => SystemCursor::No,
+        // sdl3 only exposes the two-way resize cursors.
+        CursorIcon::EResize | CursorIcon::WResize | CursorIcon::EwResize | CursorIcon::ColResize => {
+            SystemCursor::SizeWE
+        }
+        CursorIcon::NResize | CursorIcon::SResize | CursorIcon::NsResize | CursorIcon::RowResize => {
+            SystemCursor::SizeNS
+        }
+        CursorIcon::NeResize | CursorIcon::SwResize | CursorIcon::NeswResize => SystemCursor::SizeNESW,
+        CursorIcon::NwResize | CursorIcon::SeResize | CursorIcon::NwseResize => SystemCursor::SizeNWSE,
+    }
+}
+
 fn map_modifiers(m: Mod) -> KeyboardModifiers {
     KeyboardModifiers {
         alt: m.intersects(Mod::LALTMOD | Mod::RALTMOD),
@@ -136,6 +722,66 @@ fn map_modifiers(m: Mod) -> KeyboardModifiers {
     }
 }
 
+/// Map the character keys of a layout, the other keys are the same whatever the layout.
+fn map_keycode(keycode: Keycode) -> Option<KeyCode> {
+    use KeyCode as K;
+    Some(match keycode {
+        Keycode::A => K::KeyA,
+        Keycode::B => K::KeyB,
+        Keycode::C => K::KeyC,
+        Keycode::D => K::KeyD,
+        Keycode::E => K::KeyE,
+        Keycode::F => K::KeyF,
+        Keycode::G => K::KeyG,
+        Keycode::H => K::KeyH,
+        Keycode::I => K::KeyI,
+        Keycode::J => K::KeyJ,
+        Keycode::K => K::KeyK,
+        Keycode::L => K::KeyL,
+        Keycode::M => K::KeyM,
+        Keycode::N => K::KeyN,
+        Keycode::O => K::KeyO,
+        Keycode::P => K::KeyP,
+        Keycode::Q => K::KeyQ,
+        Keycode::R => K::KeyR,
+        Keycode::S => K::KeyS,
+        Keycode::T => K::KeyT,
+        Keycode::U => K::KeyU,
+        Keycode::V => K::KeyV,
+        Keycode::W => K::KeyW,
+        Keycode::X => K::KeyX,
+        Keycode::Y => K::KeyY,
+        Keycode::Z => K::KeyZ,
+        Keycode::_0 => K::Digit0,
+        Keycode::_1 => K::Digit1,
+        Keycode::_2 => K::Digit2,
+        Keycode::_3 => K::Digit3,
+        Keycode::_4 => K::Digit4,
+        Keycode::_5 => K::Digit5,
+        Keycode::_6 => K::Digit6,
+        Keycode::_7 => K::Digit7,
+        Keycode::_8 => K::Digit8,
+        Keycode::_9 => K::Digit9,
+        Keycode::Minus => K::Minus,
+        Keycode::Equals => K::Equal,
+        Keycode::LeftBracket => K::BracketLeft,
+        Keycode::RightBracket => K::BracketRight,
+        Keycode::Backslash => K::Backslash,
+        Keycode::Grave => K::Backquote,
+        Keycode::Semicolon => K::Semicolon,
+        Keycode::Apostrophe => K::Quote,
+        Keycode::Comma => K::Comma,
+        Keycode::Period => K::Period,
+        Keycode::Slash => K::Slash,
+        _ => return None,
+    })
+}
+
+/// Map a key by the character `keycode` the layout gives it, falling back to its scancode `sc`.
+fn map_layout_key(keycode: Option<Keycode>, sc: Scancode) -> Option<KeyCode> {
+    keycode.and_then(map_keycode).or_else(|| map_scancode(sc))
+}
+
 fn map_scancode(sc: Scancode) -> Option<KeyCode> {
     use KeyCode as K;
     Some(match sc {
@@ -189,6 +835,19 @@ fn map_scancode(sc: Scancode) -> Option<KeyCode> {
         Scancode::Comma => K::Comma,
         Scancode::Period => K::Period,
         Scancode::Slash => K::Slash,
+        // The ISO key left of Return shares its code with the ANSI backslash.
+        Scancode::NonUsHash => K::Backslash,
+        Scancode::NonUsBackslash => K::IntlBackslash,
+        Scancode::International1 => K::IntlRo,
+        Scancode::International2 => K::KanaMode,
+        Scancode::International3 => K::IntlYen,
+        Scancode::International4 => K::Convert,
+        Scancode::International5 => K::NonConvert,
+        Scancode::Lang1 => K::Lang1,
+        Scancode::Lang2 => K::Lang2,
+        Scancode::Lang3 => K::Lang3,
+        Scancode::Lang4 => K::Lang4,
+        Scancode::Lang5 => K::Lang5,
         // Editing / navigation
         Scancode::Return => K::Enter,
         Scancode::Escape => K::Escape,
@@ -205,6 +864,18 @@ fn map_scancode(sc: Scancode) -> Option<KeyCode> {
         Scancode::Down => K::ArrowDown,
         Scancode::Left => K::ArrowLeft,
         Scancode::Right => K::ArrowRight,
+        Scancode::Help => K::Help,
+        // Lock and system keys
+        Scancode::CapsLock => K::CapsLock,
+        Scancode::NumLockClear => K::NumLock,
+        Scancode::ScrollLock => K::ScrollLock,
+        Scancode::PrintScreen => K::PrintScreen,
+        Scancode::Pause => K::Pause,
+        Scancode::Application => K::ContextMenu,
+        Scancode::Menu => K::ContextMenu,
+        Scancode::Power => K::Power,
+        Scancode::Sleep => K::Sleep,
+        Scancode::Wake => K::WakeUp,
         // Function keys
         Scancode::F1 => K::F1,
         Scancode::F2 => K::F2,
@@ -218,6 +889,28 @@ fn map_scancode(sc: Scancode) -> Option<KeyCode> {
         Scancode::F10 => K::F10,
         Scancode::F11 => K::F11,
         Scancode::F12 => K::F12,
+        Scancode::F13 => K::F13,
+        Scancode::F14 => K::F14,
+        Scancode::F15 => K::F15,
+        Scancode::F16 => K::F16,
+        Scancode::F17 => K::F17,
+        Scancode::F18 => K::F18,
+        Scancode::F19 => K::F19,
+        Scancode::F20 => K::F20,
+        Scancode::F21 => K::F21,
+        Scancode::F22 => K::F22,
+        Scancode::F23 => K::F23,
+        Scancode::F24 => K::F24,
+        // Editing commands
+        Scancode::Execute => K::Open,
+        Scancode::Select => K::Select,
+        Scancode::Cancel => K::Abort,
+        Scancode::Again => K::Again,
+        Scancode::Undo => K::Undo,
+        Scancode::Cut => K::Cut,
+        Scancode::Copy => K::Copy,
+        Scancode::Paste => K::Paste,
+        Scancode::Find => K::Find,
         // Modifiers
         Scancode::LShift => K::ShiftLeft,
         Scancode::RShift => K::ShiftRight,
@@ -227,7 +920,7 @@ fn map_scancode(sc: Scancode) -> Option<KeyCode> {
         Scancode::RAlt => K::AltRight,
         Scancode::LGui => K::SuperLeft,
         Scancode::RGui => K::SuperRight,
-        // Keypad (map common ones)
+        // Keypad
         Scancode::Kp0 => K::Numpad0,
         Scancode::Kp1 => K::Numpad1,
         Scancode::Kp2 => K::Numpad2,
@@ -244,7 +937,635 @@ fn map_scancode(sc: Scancode) -> Option<KeyCode> {
         Scancode::KpDivide => K::NumpadDivide,
         Scancode::KpEnter => K::NumpadEnter,
         Scancode::KpPeriod => K::NumpadDecimal,
-        // Fallback
+        Scancode::KpEquals => K::NumpadEqual,
+        Scancode::KpEqualsAs400 => K::NumpadEqual,
+        Scancode::KpComma => K::NumpadComma,
+        Scancode::KpLeftParen => K::NumpadParenLeft,
+        Scancode::KpRightParen => K::NumpadParenRight,
+        Scancode::KpBackspace => K::NumpadBackspace,
+        Scancode::KpHash => K::NumpadHash,
+        Scancode::KpClear => K::NumpadClear,
+        Scancode::KpClearEntry => K::NumpadClearEntry,
+        Scancode::KpMemStore => K::NumpadMemoryStore,
+        Scancode::KpMemRecall => K::NumpadMemoryRecall,
+        Scancode::KpMemClear => K::NumpadMemoryClear,
+        Scancode::KpMemAdd => K::NumpadMemoryAdd,
+        Scancode::KpMemSubtract => K::NumpadMemorySubtract,
+        // Media and volume
+        Scancode::Mute => K::AudioVolumeMute,
+        Scancode::VolumeUp => K::AudioVolumeUp,
+        Scancode::VolumeDown => K::AudioVolumeDown,
+        Scancode::MediaPlayPause => K::MediaPlayPause,
+        Scancode::MediaPlay => K::MediaPlayPause,
+        Scancode::MediaPause => K::MediaPlayPause,
+        Scancode::MediaStop => K::MediaStop,
+        Scancode::MediaNextTrack => K::MediaTrackNext,
+        Scancode::MediaPreviousTrack => K::MediaTrackPrevious,
+        Scancode::MediaSelect => K::MediaSelect,
+        Scancode::MediaEject => K::Eject,
+        // Application control
+        Scancode::AcOpen => K::Open,
+        Scancode::AcProperties => K::Props,
+        Scancode::AcSearch => K::BrowserSearch,
+        Scancode::AcHome => K::BrowserHome,
+        Scancode::AcBack => K::BrowserBack,
+        Scancode::AcForward => K::BrowserForward,
+        Scancode::Stop => K::BrowserStop,
+        Scancode::AcStop => K::BrowserStop,
+        Scancode::AcRefresh => K::BrowserRefresh,
+        Scancode::AcBookmarks => K::BrowserFavorites,
+        // No fyrox counterpart
         _ => return None,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fyrox_ui::text_box::TextBoxBuilder;
+    use fyrox_ui::widget::WidgetBuilder;
+    use std::str::FromStr;
+    use strum::VariantNames;
+
+    /// Every scancode with a fyrox counterpart, the others must map to `None`.
+    const KEYS: &[(Scancode, KeyCode)] = &[
+        (Scancode::A, KeyCode::KeyA),
+        (Scancode::B, KeyCode::KeyB),
+        (Scancode::C, KeyCode::KeyC),
+        (Scancode::D, KeyCode::KeyD),
+        (Scancode::E, KeyCode::KeyE),
+        (Scancode::F, KeyCode::KeyF),
+        (Scancode::G, KeyCode::KeyG),
+        (Scancode::H, KeyCode::KeyH),
+        (Scancode::I, KeyCode::KeyI),
+        (Scancode::J, KeyCode::KeyJ),
+        (Scancode::K, KeyCode::KeyK),
+        (Scancode::L, KeyCode::KeyL),
+        (Scancode::M, KeyCode::KeyM),
+        (Scancode::N, KeyCode::KeyN),
+        (Scancode::O, KeyCode::KeyO),
+        (Scancode::P, KeyCode::KeyP),
+        (Scancode::Q, KeyCode::KeyQ),
+        (Scancode::R, KeyCode::KeyR),
+        (Scancode::S, KeyCode::KeyS),
+        (Scancode::T, KeyCode::KeyT),
+        (Scancode::U, KeyCode::KeyU),
+        (Scancode::V, KeyCode::KeyV),
+        (Scancode::W, KeyCode::KeyW),
+        (Scancode::X, KeyCode::KeyX),
+        (Scancode::Y, KeyCode::KeyY),
+        (Scancode::Z, KeyCode::KeyZ),
+        (Scancode::_0, KeyCode::Digit0),
+        (Scancode::_1, KeyCode::Digit1),
+        (Scancode::_2, KeyCode::Digit2),
+        (Scancode::_3, KeyCode::Digit3),
+        (Scancode::_4, KeyCode::Digit4),
+        (Scancode::_5, KeyCode::Digit5),
+        (Scancode::_6, KeyCode::Digit6),
+        (Scancode::_7, KeyCode::Digit7),
+        (Scancode::_8, KeyCode::Digit8),
+        (Scancode::_9, KeyCode::Digit9),
+        (Scancode::Minus, KeyCode::Minus),
+        (Scancode::Equals, KeyCode::Equal),
+        (Scancode::LeftBracket, KeyCode::BracketLeft),
+        (Scancode::RightBracket, KeyCode::BracketRight),
+        (Scancode::Backslash, KeyCode::Backslash),
+        (Scancode::Grave, KeyCode::Backquote),
+        (Scancode::Semicolon, KeyCode::Semicolon),
+        (Scancode::Apostrophe, KeyCode::Quote),
+        (Scancode::Comma, KeyCode::Comma),
+        (Scancode::Period, KeyCode::Period),
+        (Scancode::Slash, KeyCode::Slash),
+        (Scancode::NonUsHash, KeyCode::Backslash),
+        (Scancode::NonUsBackslash, KeyCode::IntlBackslash),
+        (Scancode::International1, KeyCode::IntlRo),
+        (Scancode::International2, KeyCode::KanaMode),
+        (Scancode::International3, KeyCode::IntlYen),
+        (Scancode::International4, KeyCode::Convert),
+        (Scancode::International5, KeyCode::NonConvert),
+        (Scancode::Lang1, KeyCode::Lang1),
+        (Scancode::Lang2, KeyCode::Lang2),
+        (Scancode::Lang3, KeyCode::Lang3),
+        (Scancode::Lang4, KeyCode::Lang4),
+        (Scancode::Lang5, KeyCode::Lang5),
+        (Scancode::Return, KeyCode::Enter),
+        (Scancode::Escape, KeyCode::Escape),
+        (Scancode::Backspace, KeyCode::Backspace),
+        (Scancode::Tab, KeyCode::Tab),
+        (Scancode::Space, KeyCode::Space),
+        (Scancode::Insert, KeyCode::Insert),
+        (Scancode::Delete, KeyCode::Delete),
+        (Scancode::Home, KeyCode::Home),
+        (Scancode::End, KeyCode::End),
+        (Scancode::PageUp, KeyCode::PageUp),
+        (Scancode::PageDown, KeyCode::PageDown),
+        (Scancode::Up, KeyCode::ArrowUp),
+        (Scancode::Down, KeyCode::ArrowDown),
+        (Scancode::Left, KeyCode::ArrowLeft),
+        (Scancode::Right, KeyCode::ArrowRight),
+        (Scancode::Help, KeyCode::Help),
+        (Scancode::CapsLock, KeyCode::CapsLock),
+        (Scancode::NumLockClear, KeyCode::NumLock),
+        (Scancode::ScrollLock, KeyCode::ScrollLock),
+        (Scancode::PrintScreen, KeyCode::PrintScreen),
+        (Scancode::Pause, KeyCode::Pause),
+        (Scancode::Application, KeyCode::ContextMenu),
+        (Scancode::Menu, KeyCode::ContextMenu),
+        (Scancode::Power, KeyCode::Power),
+        (Scancode::Sleep, KeyCode::Sleep),
+        (Scancode::Wake, KeyCode::WakeUp),
+        (Scancode::F1, KeyCode::F1),
+        (Scancode::F2, KeyCode::F2),
+        (Scancode::F3, KeyCode::F3),
+        (Scancode::F4, KeyCode::F4),
+        (Scancode::F5, KeyCode::F5),
+        (Scancode::F6, KeyCode::F6),
+        (Scancode::F7, KeyCode::F7),
+        (Scancode::F8, KeyCode::F8),
+        (Scancode::F9, KeyCode::F9),
+        (Scancode::F10, KeyCode::F10),
+        (Scancode::F11, KeyCode::F11),
+        (Scancode::F12, KeyCode::F12),
+        (Scancode::F13, KeyCode::F13),
+        (Scancode::F14, KeyCode::F14),
+        (Scancode::F15, KeyCode::F15),
+        (Scancode::F16, KeyCode::F16),
+        (Scancode::F17, KeyCode::F17),
+        (Scancode::F18, KeyCode::F18),
+        (Scancode::F19, KeyCode::F19),
+        (Scancode::F20, KeyCode::F20),
+        (Scancode::F21, KeyCode::F21),
+        (Scancode::F22, KeyCode::F22),
+        (Scancode::F23, KeyCode::F23),
+        (Scancode::F24, KeyCode::F24),
+        (Scancode::Execute, KeyCode::Open),
+        (Scancode::Select, KeyCode::Select),
+        (Scancode::Cancel, KeyCode::Abort),
+        (Scancode::Again, KeyCode::Again),
+        (Scancode::Undo, KeyCode::Undo),
+        (Scancode::Cut, KeyCode::Cut),
+        (Scancode::Copy, KeyCode::Copy),
+        (Scancode::Paste, KeyCode::Paste),
+        (Scancode::Find, KeyCode::Find),
+        (Scancode::LShift, KeyCode::ShiftLeft),
+        (Scancode::RShift, KeyCode::ShiftRight),
+        (Scancode::LCtrl, KeyCode::ControlLeft),
+        (Scancode::RCtrl, KeyCode::ControlRight),
+        (Scancode::LAlt, KeyCode::AltLeft),
+        (Scancode::RAlt, KeyCode::AltRight),
+        (Scancode::LGui, KeyCode::SuperLeft),
+        (Scancode::RGui, KeyCode::SuperRight),
+        (Scancode::Kp0, KeyCode::Numpad0),
+        (Scancode::Kp1, KeyCode::Numpad1),
+        (Scancode::Kp2, KeyCode::Numpad2),
+        (Scancode::Kp3, KeyCode::Numpad3),
+        (Scancode::Kp4, KeyCode::Numpad4),
+        (Scancode::Kp5, KeyCode::Numpad5),
+        (Scancode::Kp6, KeyCode::Numpad6),
+        (Scancode::Kp7, KeyCode::Numpad7),
+        (Scancode::Kp8, KeyCode::Numpad8),
+        (Scancode::Kp9, KeyCode::Numpad9),
+        (Scancode::KpPlus, KeyCode::NumpadAdd),
+        (Scancode::KpMinus, KeyCode::NumpadSubtract),
+        (Scancode::KpMultiply, KeyCode::NumpadMultiply),
+        (Scancode::KpDivide, KeyCode::NumpadDivide),
+        (Scancode::KpEnter, KeyCode::NumpadEnter),
+        (Scancode::KpPeriod, KeyCode::NumpadDecimal),
+        (Scancode::KpEquals, KeyCode::NumpadEqual),
+        (Scancode::KpEqualsAs400, KeyCode::NumpadEqual),
+        (Scancode::KpComma, KeyCode::NumpadComma),
+        (Scancode::KpLeftParen, KeyCode::NumpadParenLeft),
+        (Scancode::KpRightParen, KeyCode::NumpadParenRight),
+        (Scancode::KpBackspace, KeyCode::NumpadBackspace),
+        (Scancode::KpHash, KeyCode::NumpadHash),
+        (Scancode::KpClear, KeyCode::NumpadClear),
+        (Scancode::KpClearEntry, KeyCode::NumpadClearEntry),
+        (Scancode::KpMemStore, KeyCode::NumpadMemoryStore),
+        (Scancode::KpMemRecall, KeyCode::NumpadMemoryRecall),
+        (Scancode::KpMemClear, KeyCode::NumpadMemoryClear),
+        (Scancode::KpMemAdd, KeyCode::NumpadMemoryAdd),
+        (Scancode::KpMemSubtract, KeyCode::NumpadMemorySubtract),
+        (Scancode::Mute, KeyCode::AudioVolumeMute),
+        (Scancode::VolumeUp, KeyCode::AudioVolumeUp),
+        (Scancode::VolumeDown, KeyCode::AudioVolumeDown),
+        (Scancode::MediaPlayPause, KeyCode::MediaPlayPause),
+        (Scancode::MediaPlay, KeyCode::MediaPlayPause),
+        (Scancode::MediaPause, KeyCode::MediaPlayPause),
+        (Scancode::MediaStop, KeyCode::MediaStop),
+        (Scancode::MediaNextTrack, KeyCode::MediaTrackNext),
+        (Scancode::MediaPreviousTrack, KeyCode::MediaTrackPrevious),
+        (Scancode::MediaSelect, KeyCode::MediaSelect),
+        (Scancode::MediaEject, KeyCode::Eject),
+        (Scancode::AcOpen, KeyCode::Open),
+        (Scancode::AcProperties, KeyCode::Props),
+        (Scancode::AcSearch, KeyCode::BrowserSearch),
+        (Scancode::AcHome, KeyCode::BrowserHome),
+        (Scancode::AcBack, KeyCode::BrowserBack),
+        (Scancode::AcForward, KeyCode::BrowserForward),
+        (Scancode::Stop, KeyCode::BrowserStop),
+        (Scancode::AcStop, KeyCode::BrowserStop),
+        (Scancode::AcRefresh, KeyCode::BrowserRefresh),
+        (Scancode::AcBookmarks, KeyCode::BrowserFavorites),
+    ];
+
+    /// Fyrox keys SDL has no scancode for.
+    const UNMAPPED: &[KeyCode] = &[
+        KeyCode::Unknown,
+        KeyCode::Fn,
+        KeyCode::FnLock,
+        KeyCode::Meta,
+        KeyCode::Hyper,
+        KeyCode::Turbo,
+        KeyCode::Resume,
+        KeyCode::Suspend,
+        KeyCode::Hiragana,
+        KeyCode::Katakana,
+        KeyCode::NumpadStar,
+        KeyCode::LaunchApp1,
+        KeyCode::LaunchApp2,
+        KeyCode::LaunchMail,
+        KeyCode::F25,
+        KeyCode::F26,
+        KeyCode::F27,
+        KeyCode::F28,
+        KeyCode::F29,
+        KeyCode::F30,
+        KeyCode::F31,
+        KeyCode::F32,
+        KeyCode::F33,
+        KeyCode::F34,
+        KeyCode::F35,
+    ];
+
+    /// Every keycode with a fyrox counterpart, the others must map to `None`.
+    const CHARACTER_KEYS: &[(Keycode, KeyCode)] = &[
+        (Keycode::A, KeyCode::KeyA),
+        (Keycode::B, KeyCode::KeyB),
+        (Keycode::C, KeyCode::KeyC),
+        (Keycode::D, KeyCode::KeyD),
+        (Keycode::E, KeyCode::KeyE),
+        (Keycode::F, KeyCode::KeyF),
+        (Keycode::G, KeyCode::KeyG),
+        (Keycode::H, KeyCode::KeyH),
+        (Keycode::I, KeyCode::KeyI),
+        (Keycode::J, KeyCode::KeyJ),
+        (Keycode::K, KeyCode::KeyK),
+        (Keycode::L, KeyCode::KeyL),
+        (Keycode::M, KeyCode::KeyM),
+        (Keycode::N, KeyCode::KeyN),
+        (Keycode::O, KeyCode::KeyO),
+        (Keycode::P, KeyCode::KeyP),
+        (Keycode::Q, KeyCode::KeyQ),
+        (Keycode::R, KeyCode::KeyR),
+        (Keycode::S, KeyCode::KeyS),
+        (Keycode::T, KeyCode::KeyT),
+        (Keycode::U, KeyCode::KeyU),
+        (Keycode::V, KeyCode::KeyV),
+        (Keycode::W, KeyCode::KeyW),
+        (Keycode::X, KeyCode::KeyX),
+        (Keycode::Y, KeyCode::KeyY),
+        (Keycode::Z, KeyCode::KeyZ),
+        (Keycode::_0, KeyCode::Digit0),
+        (Keycode::_1, KeyCode::Digit1),
+        (Keycode::_2, KeyCode::Digit2),
+        (Keycode::_3, KeyCode::Digit3),
+        (Keycode::_4, KeyCode::Digit4),
+        (Keycode::_5, KeyCode::Digit5),
+        (Keycode::_6, KeyCode::Digit6),
+        (Keycode::_7, KeyCode::Digit7),
+        (Keycode::_8, KeyCode::Digit8),
+        (Keycode::_9, KeyCode::Digit9),
+        (Keycode::Minus, KeyCode::Minus),
+        (Keycode::Equals, KeyCode::Equal),
+        (Keycode::LeftBracket, KeyCode::BracketLeft),
+        (Keycode::RightBracket, KeyCode::BracketRight),
+        (Keycode::Backslash, KeyCode::Backslash),
+        (Keycode::Grave, KeyCode::Backquote),
+        (Keycode::Semicolon, KeyCode::Semicolon),
+        (Keycode::Apostrophe, KeyCode::Quote),
+        (Keycode::Comma, KeyCode::Comma),
+        (Keycode::Period, KeyCode::Period),
+        (Keycode::Slash, KeyCode::Slash),
+    ];
+
+    #[test]
+    fn scancodes_map_to_listed_keys() {
+        for raw in 0..Scancode::Count as i32 {
+            let Some(sc) = Scancode::from_i32(raw) else {
+                continue;
+            };
+            let expected = KEYS.iter().find(|&&(key_sc, _)| key_sc == sc).map(|&(_, key)| key);
+            assert_eq!(map_scancode(sc), expected, "{sc:?}");
+        }
+    }
+
+    #[test]
+    fn every_key_code_round_trips() {
+        for name in KeyCode::VARIANTS {
+            let key = KeyCode::from_str(name).unwrap();
+            let scancodes: Vec<_> = KEYS.iter().filter(|&&(_, k)| k == key).map(|&(sc, _)| sc).collect();
+            if UNMAPPED.contains(&key) {
+                assert!(
+                    scancodes.is_empty(),
+                    "{name} is listed as unmapped but has {scancodes:?}"
+                );
+                continue;
+            }
+            assert!(!scancodes.is_empty(), "{name} has no scancode");
+            for sc in scancodes {
+                assert_eq!(map_scancode(sc), Some(key), "{sc:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn keycodes_map_to_listed_keys() {
+        // Character keycodes are their ASCII code, the others their scancode with a mask bit.
+        let scancode_mask = Keycode::ScancodeMask as i32;
+        let raw_keycodes = (0..0x80).chain((0..Scancode::Count as i32).map(|sc| sc | scancode_mask));
+        for raw in raw_keycodes {
+            let Some(keycode) = Keycode::from_i32(raw) else {
+                continue;
+            };
+            let expected = CHARACTER_KEYS
+                .iter()
+                .find(|&&(key_keycode, _)| key_keycode == keycode)
+                .map(|&(_, key)| key);
+            assert_eq!(map_keycode(keycode), expected, "{keycode:?}");
+        }
+    }
+
+    #[test]
+    fn layout_keys_fall_back_to_the_scancode() {
+        let cases = [
+            // The key right of Tab on AZERTY.
+            (Some(Keycode::A), Scancode::Q, Some(KeyCode::KeyA)),
+            // Digits are shifted on AZERTY, the unshifted `&` has no fyrox key.
+            (Some(Keycode::Ampersand), Scancode::_1, Some(KeyCode::Digit1)),
+            // Characters without a keycode, e.g. `é`.
+            (None, Scancode::_2, Some(KeyCode::Digit2)),
+            // Keys without a character.
+            (Some(Keycode::F1), Scancode::F1, Some(KeyCode::F1)),
+            (Some(Keycode::LCtrl), Scancode::LCtrl, Some(KeyCode::ControlLeft)),
+            (None, Scancode::Unknown, None),
+        ];
+        for (keycode, sc, expected) in cases {
+            assert_eq!(map_layout_key(keycode, sc), expected, "{keycode:?} {sc:?}");
+        }
+    }
+
+    fn key_event(sc: Scancode, keymod: Mod, pressed: bool) -> Event {
+        let (timestamp, window_id, keycode, repeat, which, raw) = (0, 1, None, false, 0, 0);
+        let scancode = Some(sc);
+        if pressed {
+            Event::KeyDown {
+                timestamp,
+                window_id,
+                keycode,
+                scancode,
+                keymod,
+                repeat,
+                which,
+                raw,
+            }
+        } else {
+            Event::KeyUp {
+                timestamp,
+                window_id,
+                keycode,
+                scancode,
+                keymod,
+                repeat,
+                which,
+                raw,
+            }
+        }
+    }
+
+    fn button_event(mouse_btn: SdlMouseButton, pressed: bool) -> Event {
+        let (timestamp, window_id, which, clicks, x, y) = (0, 1, 0, 1, 0.0, 0.0);
+        if pressed {
+            Event::MouseButtonDown {
+                timestamp,
+                window_id,
+                which,
+                mouse_btn,
+                clicks,
+                x,
+                y,
+            }
+        } else {
+            Event::MouseButtonUp {
+                timestamp,
+                window_id,
+                which,
+                mouse_btn,
+                clicks,
+                x,
+                y,
+            }
+        }
+    }
+
+    fn window_event(window_id: u32, win_event: WindowEvent) -> Event {
+        Event::Window {
+            timestamp: 0,
+            window_id,
+            win_event,
+        }
+    }
+
+    #[test]
+    fn pressed_inputs_are_tracked_until_released() {
+        let mut ui = UserInterface::new(Vector2::new(100.0, 100.0));
+        let mut platform = Platform::new();
+
+        platform.handle_event(&mut ui, &key_event(Scancode::A, Mod::NOMOD, true));
+        // Key repeats do not add the key twice.
+        platform.handle_event(&mut ui, &key_event(Scancode::A, Mod::NOMOD, true));
+        platform.handle_event(&mut ui, &key_event(Scancode::B, Mod::NOMOD, true));
+        platform.handle_event(&mut ui, &button_event(SdlMouseButton::Left, true));
+        platform.handle_event(&mut ui, &button_event(SdlMouseButton::Right, true));
+        assert_eq!(platform.pressed_keys, [KeyCode::KeyA, KeyCode::KeyB]);
+        assert_eq!(platform.pressed_buttons, [MouseButton::Left, MouseButton::Right]);
+
+        platform.handle_event(&mut ui, &key_event(Scancode::A, Mod::NOMOD, false));
+        platform.handle_event(&mut ui, &button_event(SdlMouseButton::Right, false));
+        assert_eq!(platform.pressed_keys, [KeyCode::KeyB]);
+        assert_eq!(platform.pressed_buttons, [MouseButton::Left]);
+    }
+
+    #[test]
+    fn release_inputs_forgets_pressed_inputs_and_modifiers() {
+        let mut ui = UserInterface::new(Vector2::new(100.0, 100.0));
+        let mut platform = Platform::new();
+
+        platform.handle_event(&mut ui, &key_event(Scancode::LShift, Mod::LSHIFTMOD, true));
+        platform.handle_event(&mut ui, &button_event(SdlMouseButton::Left, true));
+        assert!(ui.keyboard_modifiers().shift);
+
+        platform.release_inputs(&mut ui);
+        assert!(platform.pressed_keys.is_empty());
+        assert!(platform.pressed_buttons.is_empty());
+        assert_eq!(ui.keyboard_modifiers(), KeyboardModifiers::default());
+    }
+
+    #[test]
+    fn window_events_of_other_windows_are_ignored() {
+        let mut ui = UserInterface::new(Vector2::new(100.0, 100.0));
+        let mut platform = Platform::new();
+        platform.window_id = Some(1);
+        platform.handle_event(&mut ui, &key_event(Scancode::A, Mod::NOMOD, true));
+
+        assert!(!platform.handle_event(&mut ui, &window_event(2, WindowEvent::FocusLost)));
+        assert!(!platform.handle_event(&mut ui, &window_event(2, WindowEvent::Minimized)));
+        assert_eq!(platform.pressed_keys, [KeyCode::KeyA]);
+        assert!(!platform.is_minimized());
+
+        assert!(platform.handle_event(&mut ui, &window_event(1, WindowEvent::FocusLost)));
+        assert!(platform.handle_event(&mut ui, &window_event(1, WindowEvent::Minimized)));
+        assert!(platform.pressed_keys.is_empty());
+        assert!(platform.is_minimized());
+    }
+
+    #[test]
+    fn cursor_icons_map_to_the_closest_system_cursor() {
+        let cases = [
+            (CursorIcon::Default, SystemCursor::Arrow),
+            (CursorIcon::ContextMenu, SystemCursor::Arrow),
+            (CursorIcon::Help, SystemCursor::Arrow),
+            (CursorIcon::Alias, SystemCursor::Arrow),
+            (CursorIcon::Copy, SystemCursor::Arrow),
+            (CursorIcon::ZoomIn, SystemCursor::Arrow),
+            (CursorIcon::ZoomOut, SystemCursor::Arrow),
+            (CursorIcon::Pointer, SystemCursor::Hand),
+            (CursorIcon::Grab, SystemCursor::Hand),
+            (CursorIcon::Grabbing, SystemCursor::Hand),
+            (CursorIcon::Progress, SystemCursor::WaitArrow),
+            (CursorIcon::Wait, SystemCursor::Wait),
+            (CursorIcon::Cell, SystemCursor::Crosshair),
+            (CursorIcon::Crosshair, SystemCursor::Crosshair),
+            (CursorIcon::Text, SystemCursor::IBeam),
+            (CursorIcon::VerticalText, SystemCursor::IBeam),
+            (CursorIcon::Move, SystemCursor::SizeAll),
+            (CursorIcon::AllScroll, SystemCursor::SizeAll),
+            (CursorIcon::NoDrop, SystemCursor::No),
+            (CursorIcon::NotAllowed, SystemCursor::No),
+            (CursorIcon::EResize, SystemCursor::SizeWE),
+            (CursorIcon::WResize, SystemCursor::SizeWE),
+            (CursorIcon::EwResize, SystemCursor::SizeWE),
+            (CursorIcon::ColResize, SystemCursor::SizeWE),
+            (CursorIcon::NResize, SystemCursor::SizeNS),
+            (CursorIcon::SResize, SystemCursor::SizeNS),
+            (CursorIcon::NsResize, SystemCursor::SizeNS),
+            (CursorIcon::RowResize, SystemCursor::SizeNS),
+            (CursorIcon::NeResize, SystemCursor::SizeNESW),
+            (CursorIcon::SwResize, SystemCursor::SizeNESW),
+            (CursorIcon::NeswResize, SystemCursor::SizeNESW),
+            (CursorIcon::NwResize, SystemCursor::SizeNWSE),
+            (CursorIcon::SeResize, SystemCursor::SizeNWSE),
+            (CursorIcon::NwseResize, SystemCursor::SizeNWSE),
+        ];
+        for name in CursorIcon::VARIANTS {
+            let icon = CursorIcon::from_str(name).unwrap();
+            assert!(cases.iter().any(|&(listed, _)| listed == icon), "{name} is not listed");
+        }
+        for (icon, cursor) in cases {
+            assert_eq!(map_cursor_icon(icon), cursor, "{icon:?}");
+        }
+    }
+
+    fn text_box_ui(text: &str) -> (UserInterface, Platform) {
+        let mut ui = UserInterface::new(Vector2::new(100.0, 100.0));
+        let text_box = TextBoxBuilder::new(WidgetBuilder::new())
+            .with_text(text)
+            .build(&mut ui.build_ctx());
+        let mut platform = Platform::new();
+        platform.text_box = text_box;
+        (ui, platform)
+    }
+
+    fn text_of(ui: &UserInterface, platform: &Platform) -> String {
+        let text_box = ui
+            .nodes()
+            .try_borrow(platform.text_box)
+            .and_then(|node| node.cast::<TextBox>());
+        text_box.unwrap().formatted_text.borrow().text()
+    }
+
+    fn set_text(ui: &mut UserInterface, platform: &Platform, text: &str) {
+        let text_box = ui
+            .try_get_node_mut(platform.text_box)
+            .and_then(|node| node.cast_mut::<TextBox>());
+        text_box.unwrap().formatted_text.borrow_mut().set_text(text).build();
+    }
+
+    /// Keys released on the focused widget since the last call.
+    fn released_keys(ui: &mut UserInterface) -> Vec<KeyCode> {
+        std::iter::from_fn(|| ui.poll_message())
+            .filter_map(|message| match message.data::<WidgetMessage>() {
+                Some(&WidgetMessage::KeyUp(key)) => Some(key),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn only_pressed_keys_are_released() {
+        let (mut ui, mut platform) = text_box_ui("ab");
+        ui.send_message(WidgetMessage::focus(platform.text_box, MessageDirection::ToWidget));
+        released_keys(&mut ui);
+
+        platform.handle_event(&mut ui, &key_event(Scancode::A, Mod::NOMOD, false));
+        assert_eq!(released_keys(&mut ui), []);
+
+        platform.handle_event(&mut ui, &key_event(Scancode::A, Mod::NOMOD, true));
+        platform.handle_event(&mut ui, &key_event(Scancode::A, Mod::NOMOD, false));
+        assert_eq!(released_keys(&mut ui), [KeyCode::KeyA]);
+    }
+
+    #[test]
+    fn keys_pressed_while_composing_are_not_released() {
+        let (mut ui, mut platform) = text_box_ui("ab");
+        ui.send_message(WidgetMessage::focus(platform.text_box, MessageDirection::ToWidget));
+        released_keys(&mut ui);
+
+        platform.compose(&mut ui, "x", 1);
+        platform.handle_event(&mut ui, &key_event(Scancode::A, Mod::NOMOD, true));
+        platform.cancel_composition(&mut ui);
+        platform.handle_event(&mut ui, &key_event(Scancode::A, Mod::NOMOD, false));
+        assert_eq!(released_keys(&mut ui), []);
+        assert!(platform.pressed_keys.is_empty());
+    }
+
+    #[test]
+    fn composition_is_removed_before_clicks() {
+        let (mut ui, mut platform) = text_box_ui("ab");
+        platform.compose(&mut ui, "xy", 2);
+        assert_eq!(text_of(&ui, &platform), "xyab");
+
+        platform.handle_event(&mut ui, &button_event(SdlMouseButton::Left, true));
+        assert_eq!(text_of(&ui, &platform), "ab");
+        assert!(platform.composition.is_none());
+    }
+
+    #[test]
+    fn composition_survives_replaced_text() {
+        let (mut ui, mut platform) = text_box_ui("abc");
+        platform.compose(&mut ui, "xyz", 3);
+
+        // The app sets a shorter text while composing.
+        set_text(&mut ui, &platform, "q");
+
+        // The pre-edit text restarts at the caret, clamped to the new text.
+        platform.compose(&mut ui, "x", 1);
+        assert_eq!(text_of(&ui, &platform), "qx");
+        platform.cancel_composition(&mut ui);
+        assert_eq!(text_of(&ui, &platform), "q");
+
+        platform.compose(&mut ui, "xy", 2);
+        set_text(&mut ui, &platform, "");
+        platform.cancel_composition(&mut ui);
+        assert_eq!(text_of(&ui, &platform), "");
+    }
+}
@@ -0,0 +1,231 @@
+//! Downloading rendered frames back to the CPU, e.g. for screenshots.
+//!
+//! A download is recorded at the end of the frame's command buffer, which is then submitted with
+//! a fence. The returned [`Readback`] can either be waited on right away, or polled on the next
+//! frames so the CPU never stalls on the GPU.
+//!
+//! Swapchain textures cannot be downloaded: SDL does not create them with the transfer source
+//! usage on every backend, e.g. Vulkan, and they borrow the command buffer until it is submitted.
+//! To capture a whole frame, use [`FyroxUiSdl::render_with_readback`](crate::FyroxUiSdl::render_with_readback),
+//! which renders it into a texture of the renderer, downloads that and blits it to the swapchain.
+//! Offscreen renders are downloaded from a [`ReadbackTarget`]:
+//!
+//! ```no_run
+//! # use fyrox_ui_sdl3::{FyroxUiSdl, readback::submit_with_readback};
+//! # use sdl3::gpu::{ColorTargetInfo, LoadOp, StoreOp};
+//! # fn frame(device: &sdl3::gpu::Device, fyrox_ui: &mut FyroxUiSdl) -> Result<(), fyrox_ui_sdl3::Error> {
+//! let (width, height) = (640, 480);
+//! let target = fyrox_ui.renderer.create_readback_target(device, width, height)?;
+//! let color_targets = [ColorTargetInfo::default()
+//!     .with_texture(target.texture())
+//!     .with_load_op(LoadOp::CLEAR)
+//!     .with_store_op(StoreOp::STORE)];
+//!
+//! let mut command_buffer = device.acquire_command_buffer().unwrap();
+//! let drawing = fyrox_ui.ui.draw();
+//! fyrox_ui
+//!     .renderer
+//!     .render_to_target(device, &mut command_buffer, &color_targets, None, (width, height), drawing)?;
+//!
+//! let screenshot = submit_with_readback(device, command_buffer, &target.source())?.wait()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::Error;
+use crate::utils::create_layer_target;
+use sdl3::gpu::{CommandBuffer, Device, Texture, TextureFormat, TransferBuffer, TransferBufferUsage};
+use sdl3::sys::gpu::{
+    SDL_DownloadFromGPUTexture, SDL_GPUFence, SDL_GPUTexture, SDL_GPUTextureRegion, SDL_GPUTextureTransferInfo,
+    SDL_QueryGPUFence, SDL_ReleaseGPUFence, SDL_SubmitGPUCommandBufferAndAcquireFence, SDL_WaitForGPUFences,
+};
+use std::marker::PhantomData;
+
+/// Downloaded pixels, RGBA8 rows from the top-left corner.
+#[derive(Clone, Debug)]
+pub struct Screenshot {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// Color target to render into and download, which keeps the format it was created with so the
+/// pixels are always read in the right channel order.
+#[derive(Clone)]
+pub struct ReadbackTarget {
+    texture: Texture<'static>,
+    format: TextureFormat,
+}
+
+impl ReadbackTarget {
+    /// Create a `width`×`height` target of `format`, which can also be sampled or blitted from.
+    ///
+    /// Only 8-bit RGBA and BGRA formats are supported, which covers
+    /// [`UiRenderer::format`](crate::renderer::UiRenderer::format) in the usual setups, see
+    /// [`UiRenderer::create_readback_target`](crate::renderer::UiRenderer::create_readback_target).
+    pub fn new(device: &Device, format: TextureFormat, width: u32, height: u32) -> Result<Self, Error> {
+        if bytes_order(format).is_none() {
+            return Err(Error::ReadbackFormat(format));
+        }
+        let texture = create_layer_target(device, format, width, height).map_err(Error::Upload)?;
+
+        Ok(Self { texture, format })
+    }
+
+    /// Texture to render into.
+    pub fn texture(&self) -> &Texture<'static> {
+        &self.texture
+    }
+
+    /// Format the texture was created with.
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    /// Download of the whole texture, to pass to [`submit_with_readback`].
+    pub fn source(&self) -> ReadbackSource<'_> {
+        ReadbackSource {
+            texture: self.texture.raw(),
+            width: self.texture.width(),
+            height: self.texture.height(),
+            format: self.format,
+            _texture: PhantomData,
+        }
+    }
+}
+
+/// Texture to download at the end of a frame, from [`ReadbackTarget::source`], borrowed until the
+/// command buffer is submitted.
+#[derive(Clone, Copy, Debug)]
+pub struct ReadbackSource<'a> {
+    texture: *mut SDL_GPUTexture,
+    width: u32,
+    height: u32,
+    format: TextureFormat,
+    _texture: PhantomData<&'a Texture<'a>>,
+}
+
+/// Byte order of the texel channels in memory, `None` if the format is not supported.
+fn bytes_order(format: TextureFormat) -> Option<[usize; 4]> {
+    match format {
+        TextureFormat::R8g8b8a8Unorm | TextureFormat::R8g8b8a8UnormSrgb => Some([0, 1, 2, 3]),
+        TextureFormat::B8g8r8a8Unorm | TextureFormat::B8g8r8a8UnormSrgb => Some([2, 1, 0, 3]),
+        _ => None,
+    }
+}
+
+/// Record the download of `source` at the end of `command_buffer` and submit it.
+///
+/// This replaces `command_buffer.submit()`, so it must come after every pass writing to the
+/// texture, including the UI one. The command buffer is cancelled if the download cannot be
+/// recorded, so none of the frame is executed.
+pub fn submit_with_readback(
+    device: &Device,
+    mut command_buffer: CommandBuffer,
+    source: &ReadbackSource<'_>,
+) -> Result<Readback, Error> {
+    let transfer_buffer = match device
+        .create_transfer_buffer()
+        .with_size(source.width * source.height * 4)
+        .with_usage(TransferBufferUsage::DOWNLOAD)
+        .build()
+    {
+        Ok(transfer_buffer) => transfer_buffer,
+        Err(err) => {
+            command_buffer.cancel();
+            return Err(Error::Readback(err));
+        }
+    };
+
+    let copy_pass = match device.begin_copy_pass(&command_buffer) {
+        Ok(copy_pass) => copy_pass,
+        Err(err) => {
+            command_buffer.cancel();
+            return Err(Error::Readback(err));
+        }
+    };
+    let region = SDL_GPUTextureRegion {
+        texture: source.texture,
+        w: source.width,
+        h: source.height,
+        d: 1,
+        ..Default::default()
+    };
+    let transfer_info = SDL_GPUTextureTransferInfo {
+        transfer_buffer: transfer_buffer.raw(),
+        ..Default::default()
+    };
+    unsafe {
+        SDL_DownloadFromGPUTexture(copy_pass.raw(), &region, &transfer_info);
+    }
+    device.end_copy_pass(copy_pass);
+
+    let fence = unsafe { SDL_SubmitGPUCommandBufferAndAcquireFence(command_buffer.raw()) };
+    if fence.is_null() {
+        return Err(Error::Readback(sdl3::get_error()));
+    }
+
+    Ok(Readback {
+        device: device.clone(),
+        transfer_buffer,
+        fence,
+        width: source.width,
+        height: source.height,
+        format: source.format,
+    })
+}
+
+/// Download in flight, completed once the GPU has executed the submitted command buffer.
+pub struct Readback {
+    device: Device,
+    transfer_buffer: TransferBuffer,
+    fence: *mut SDL_GPUFence,
+    width: u32,
+    height: u32,
+    format: TextureFormat,
+}
+
+impl Readback {
+    /// Whether the GPU is done and [`Readback::try_take`] will succeed.
+    pub fn is_ready(&self) -> bool {
+        unsafe { SDL_QueryGPUFence(self.device.raw(), self.fence) }
+    }
+
+    /// Take the pixels if the download is complete, otherwise give the handle back to poll it
+    /// again later, typically on the next frame.
+    pub fn try_take(self) -> Result<Screenshot, Self> {
+        if self.is_ready() { Ok(self.read()) } else { Err(self) }
+    }
+
+    /// Block until the download is complete and take the pixels.
+    pub fn wait(self) -> Result<Screenshot, Error> {
+        if !unsafe { SDL_WaitForGPUFences(self.device.raw(), true, &self.fence, 1) } {
+            return Err(Error::Readback(sdl3::get_error()));
+        }
+
+        Ok(self.read())
+    }
+
+    fn read(&self) -> Screenshot {
+        let order = bytes_order(self.format).expect("format is checked by ReadbackTarget::new");
+        let buffer_mem = self.transfer_buffer.map::<u8>(&self.device, false);
+        let pixels = buffer_mem
+            .mem()
+            .chunks_exact(4)
+            .flat_map(|texel| order.map(|channel| texel[channel]))
+            .collect();
+        buffer_mem.unmap();
+
+        Screenshot {
+            width: self.width,
+            height: self.height,
+            pixels,
+        }
+    }
+}
+
+impl Drop for Readback {
+    fn drop(&mut self) {
+        unsafe { SDL_ReleaseGPUFence(self.device.raw(), self.fence) }
+    }
+}
@@ -1,25 +1,579 @@
 //! Minimal renderer backend that converts Fyrox-UI `DrawingContext` to SDL3 GPU draw calls.
 
-use crate::utils::create_texture;
+use crate::cache::{FontPageCache, ResourceCache, TextureCache};
+use crate::error::Error;
+use crate::platform::WindowScale;
+use crate::readback::ReadbackTarget;
+use crate::utils::{
+    as_bytes, color_to_array, convert_texture_pixels, create_layer_target, create_multisample_target, create_texture,
+    create_texture_with_format, ensure_buffer, ensure_transfer_buffer, is_linear_format, texture_format,
+    update_texture, update_texture_level,
+};
+use fyrox_ui::UiNode;
+use fyrox_ui::UserInterface;
+use fyrox_ui::brush::Brush;
+use fyrox_ui::core::math::Rect;
+use fyrox_ui::core::pool::Handle;
 use fyrox_ui::draw::Command;
 use fyrox_ui::draw::CommandTexture;
 use fyrox_ui::draw::DrawingContext;
+use fyrox_ui::font::{FontHeight, FontResource};
+use fyrox_ui::texture::{TexturePixelKind, TextureResource, TextureResourceExtension};
 use sdl3::gpu::*;
-use sdl3::sys::gpu::SDL_GPUViewport;
+use sdl3::pixels::Color as ClearColor;
+use sdl3::sys::gpu::{
+    SDL_BeginGPURenderPass, SDL_BindGPUGraphicsPipeline, SDL_CreateGPUGraphicsPipeline, SDL_EndGPURenderPass,
+    SDL_GPUBlendFactor, SDL_GPUBlendOp, SDL_GPUColorTargetBlendState, SDL_GPUColorTargetDescription,
+    SDL_GPUColorTargetInfo, SDL_GPUCompareOp, SDL_GPUCullMode, SDL_GPUDepthStencilState, SDL_GPUFillMode,
+    SDL_GPUFrontFace, SDL_GPUGraphicsPipeline, SDL_GPUGraphicsPipelineCreateInfo, SDL_GPUGraphicsPipelineTargetInfo,
+    SDL_GPUMultisampleState, SDL_GPUPrimitiveType, SDL_GPURasterizerState, SDL_GPUSampleCount, SDL_GPUStencilOp,
+    SDL_GPUStencilOpState, SDL_GPUTexture, SDL_GPUTextureFormat, SDL_GPUTextureSupportsSampleCount,
+    SDL_GPUVertexAttribute, SDL_GPUVertexBufferDescription, SDL_GPUVertexElementFormat, SDL_GPUVertexInputRate,
+    SDL_GPUVertexInputState, SDL_GPUViewport, SDL_ReleaseGPUGraphicsPipeline,
+};
 use sdl3::video::Window;
+use std::collections::HashMap;
 use std::mem::offset_of;
+use std::ops::Range;
+use std::rc::{Rc, Weak};
+
+/// GPU copy of a user texture resource.
+struct TextureCacheEntry {
+    texture: Texture<'static>,
+    // Layout of the uploaded data, a change requires a new GPU texture.
+    width: u32,
+    height: u32,
+    pixel_kind: TexturePixelKind,
+    mip_count: u32,
+    // Whether the texture is sampled as linear values, see `is_linear_format`.
+    linear: bool,
+}
+
+/// GPU texture owned by the application, shown by the UI through a texture resource.
+struct ExternalTexture {
+    texture: Texture<'static>,
+    // Whether the texture is sampled as linear values, see `is_linear_format`.
+    linear: bool,
+}
+
+/// Maximum number of gradient points the fragment shader can blend, extra points are ignored.
+const MAX_GRADIENT_POINTS: usize = 16;
+
+/// Values of `Brush` variants, must match the `BRUSH_*` defines of `ui.frag`.
+const BRUSH_SOLID: i32 = 0;
+const BRUSH_LINEAR_GRADIENT: i32 = 1;
+const BRUSH_RADIAL_GRADIENT: i32 = 2;
+
+/// Per-command fragment uniforms, laid out to match `WidgetData` in `ui.frag` (std140).
+///
+/// This mirrors what Fyrox's own UI renderer writes: brush data (solid color or gradient colors,
+/// stops and points), resolution, command bounds, font flag and opacity.
+#[derive(PartialEq)]
+#[repr(C)]
+struct WidgetData {
+    // vec4 solidColor
+    solid: [f32; 4],
+    // vec4 gradientColors[16]
+    gradient_colors: [[f32; 4]; MAX_GRADIENT_POINTS],
+    // vec4 gradientStops[4], std140 pads float arrays to vec4 so stops are packed by four
+    gradient_stops: [f32; MAX_GRADIENT_POINTS],
+    // vec2 gradientOrigin
+    gradient_origin: [f32; 2],
+    // vec2 gradientEnd
+    gradient_end: [f32; 2],
+    // vec2 resolution
+    resolution: [f32; 2],
+    // vec2 boundsMin
+    bounds_min: [f32; 2],
+    // vec2 boundsMax
+    bounds_max: [f32; 2],
+    // bool isFontTexture
+    is_font: u32,
+    // float opacity
+    opacity: f32,
+    // int brushType
+    brush_type: i32,
+    // int gradientPointCount
+    gradient_point_count: i32,
+    // bool premultiply
+    premultiply: u32,
+    // bool linearOutput
+    linear_output: u32,
+    // float outputScale
+    output_scale: f32,
+    // bool linearTexture
+    linear_texture: u32,
+    // padding to 16B multiples
+    _pad: [f32; 2],
+}
+
+impl WidgetData {
+    fn new(cmd_ui: &Command, scale: &WindowScale, output: &FragmentOutput, linear_texture: bool) -> Self {
+        let (fb_w, fb_h) = scale.size_in_pixels;
+        // The shader compares bounds to framebuffer coordinates, so they're given in pixels.
+        let tl = cmd_ui.bounds.position * scale.display_scale;
+        let br = cmd_ui.bounds.right_bottom_corner() * scale.display_scale;
+        let bounds_min = [tl.x, tl.y];
+        let bounds_max = [br.x, br.y];
+
+        let mut widget = Self {
+            solid: [1.0, 1.0, 1.0, 1.0],
+            gradient_colors: [[0.0; 4]; MAX_GRADIENT_POINTS],
+            gradient_stops: [0.0; MAX_GRADIENT_POINTS],
+            gradient_origin: [0.0, 0.0],
+            gradient_end: [0.0, 0.0],
+            resolution: [fb_w as f32, fb_h as f32],
+            bounds_min,
+            bounds_max,
+            is_font: matches!(cmd_ui.texture, CommandTexture::Font { .. }) as u32,
+            opacity: cmd_ui.opacity,
+            brush_type: BRUSH_SOLID,
+            gradient_point_count: 0,
+            premultiply: output.premultiply as u32,
+            linear_output: output.linear as u32,
+            output_scale: output.scale,
+            linear_texture: linear_texture as u32,
+            _pad: [0.0; 2],
+        };
+
+        let stops = match &cmd_ui.brush {
+            Brush::Solid(color) => {
+                widget.solid = color_to_array(*color);
+                return widget;
+            }
+            Brush::LinearGradient { from, to, stops } => {
+                widget.brush_type = BRUSH_LINEAR_GRADIENT;
+                widget.gradient_origin = [from.x, from.y];
+                widget.gradient_end = [to.x, to.y];
+                stops
+            }
+            Brush::RadialGradient { center, stops } => {
+                widget.brush_type = BRUSH_RADIAL_GRADIENT;
+                widget.gradient_origin = [center.x, center.y];
+                stops
+            }
+        };
+
+        for (i, point) in stops.iter().take(MAX_GRADIENT_POINTS).enumerate() {
+            widget.gradient_colors[i] = color_to_array(point.color);
+            widget.gradient_stops[i] = point.stop;
+        }
+        widget.gradient_point_count = stops.len().min(MAX_GRADIENT_POINTS) as i32;
+
+        widget
+    }
+}
+
+/// How the fragment shader encodes its output for the color target.
+#[derive(Debug, PartialEq)]
+struct FragmentOutput {
+    // Premultiply colors by alpha, see `BlendMode`.
+    premultiply: bool,
+    // Convert fyrox colors, which are sRGB encoded, to linear values.
+    linear: bool,
+    // Factor applied to linear colors, to set the brightness of HDR output.
+    scale: f32,
+}
+
+impl FragmentOutput {
+    /// Output encoding for color targets of `format` on a swapchain set to `composition`.
+    fn new(format: TextureFormat, composition: SwapchainComposition, hdr_brightness: f32, mode: BlendMode) -> Self {
+        // sRGB targets encode on write and float targets are linear, so both expect linear values.
+        let linear = is_linear_format(format);
+        let scale = match composition {
+            SwapchainComposition::HdrExtendedLinear if linear => hdr_brightness / SCRGB_WHITE_NITS,
+            _ => 1.0,
+        };
+
+        Self {
+            premultiply: mode.premultiplied_output(),
+            linear,
+            scale,
+        }
+    }
+}
+
+/// UI uploaded by [`UiRenderer::prepare`], to record into a render pass with
+/// [`PreparedFrame::draw`].
+///
+/// It borrows the renderer and the drawing context until it is drawn, so the drawn context is
+/// always the uploaded one, and each upload is drawn once:
+///
+/// ```compile_fail,E0382
+/// # fn frame(
+/// #     device: &sdl3::gpu::Device,
+/// #     command_buffer: &mut sdl3::gpu::CommandBuffer,
+/// #     render_pass: &sdl3::gpu::RenderPass,
+/// #     renderer: &mut fyrox_ui_sdl3::renderer::UiRenderer,
+/// #     drawing: &fyrox_ui::draw::DrawingContext,
+/// # ) -> Result<(), fyrox_ui_sdl3::Error> {
+/// let scale = fyrox_ui_sdl3::platform::WindowScale::default();
+/// let prepared = renderer.prepare(device, command_buffer, drawing)?;
+/// prepared.draw(device, command_buffer, render_pass, &scale)?;
+/// prepared.draw(device, command_buffer, render_pass, &scale)?;
+/// # Ok(())
+/// # }
+/// ```
+#[must_use = "the UI is only recorded by `PreparedFrame::draw`"]
+pub struct PreparedFrame<'a> {
+    renderer: &'a mut UiRenderer,
+    drawing: &'a DrawingContext,
+}
+
+impl PreparedFrame<'_> {
+    /// Record the draws of the prepared UI into the caller's `render_pass`, so the UI can be the
+    /// last commands of an existing pass instead of a pass of its own.
+    ///
+    /// The pass must target the renderer's color format with [`UiRenderer::sample_count`]
+    /// samples, and have a depth-stencil target of the configured format with a zeroed stencil if
+    /// [`UiRendererConfig::depth_stencil_format`] is set.
+    /// `scale` describes the target, use [`WindowScale::of`] for a window, or a default scale
+    /// with the size of the texture for offscreen targets.
+    ///
+    /// The pipeline, viewport, scissor, vertex and index buffers and fragment samplers of the pass
+    /// are left in the state of the last UI draw.
+    pub fn draw(
+        self,
+        device: &Device,
+        command_buffer: &mut CommandBuffer,
+        render_pass: &RenderPass,
+        scale: &WindowScale,
+    ) -> Result<(), Error> {
+        self.renderer
+            .draw(device, command_buffer, render_pass, scale, self.drawing)
+    }
+}
+
+/// Copy pass used for uploads, begun on first use.
+type UploadPass = Option<CopyPass>;
+
+/// Statistics of the last frame drawn by a [`UiRenderer`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    /// Number of fyrox draw commands in the frame.
+    pub commands: usize,
+    /// Number of draw calls issued after merging compatible commands, including stencil clipping.
+    pub draw_calls: usize,
+}
+
+impl RenderStats {
+    /// Number of draw calls saved by batching.
+    ///
+    /// Stencil clipping costs extra draw calls, so this can be zero even when commands were merged.
+    pub fn saved_draw_calls(&self) -> usize {
+        self.commands.saturating_sub(self.draw_calls)
+    }
+}
+
+/// Brightness of UI white on HDR swapchains by default, the reference white of ITU-R BT.2408.
+pub const DEFAULT_HDR_BRIGHTNESS: f32 = 203.0;
+
+/// Luminance of the linear value 1.0 in the scRGB color space of `HdrExtendedLinear` swapchains.
+const SCRGB_WHITE_NITS: f32 = 80.0;
+
+/// Options for creating a [`UiRenderer`].
+#[derive(Clone, Debug)]
+pub struct UiRendererConfig {
+    /// Format of the depth-stencil target the UI is rendered with.
+    ///
+    /// When set, commands carrying clipping geometry are clipped through the stencil buffer and
+    /// every render call must be given a depth-stencil target of this format. When unset, only
+    /// the scissor rectangle of `clip_bounds` is used.
+    pub depth_stencil_format: Option<TextureFormat>,
+    /// Number of samples per pixel to render the UI with, for anti-aliased edges.
+    ///
+    /// Falls back to the highest lower count supported by the color and depth-stencil formats,
+    /// see [`UiRenderer::sample_count`]. With multisampling, [`UiRenderer::render`] draws into a
+    /// multisample texture, resolves it and composites the result over a single color target,
+    /// which can be loaded or cleared. The depth-stencil target must have the same sample count.
+    pub sample_count: SampleCount,
+    /// How the UI is initially composited over the target, see [`UiRenderer::set_blend_mode`].
+    pub blend_mode: BlendMode,
+    /// Composition the swapchain was set to with `Device::set_swapchain_parameters`.
+    ///
+    /// The renderer cannot query it, and the color format alone does not tell SDR and HDR linear
+    /// swapchains apart. `Hdr10St2084` is not supported. See [`UiRenderer::set_composition`].
+    pub composition: SwapchainComposition,
+    /// Luminance of UI white in nits on `HdrExtendedLinear` swapchains, ignored otherwise.
+    pub hdr_brightness: f32,
+}
+
+impl Default for UiRendererConfig {
+    fn default() -> Self {
+        Self {
+            depth_stencil_format: None,
+            sample_count: SampleCount::default(),
+            blend_mode: BlendMode::default(),
+            composition: SwapchainComposition::default(),
+            hdr_brightness: DEFAULT_HDR_BRIGHTNESS,
+        }
+    }
+}
+
+/// How UI colors are combined with the content of the color target.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    /// Straight alpha blending, for targets that are displayed as is, like the swapchain.
+    #[default]
+    Alpha,
+    /// The shader outputs premultiplied colors, blended with `One, OneMinusSrcAlpha`.
+    ///
+    /// Use this when the target is composited again afterwards, e.g. a UI texture drawn over a
+    /// scene: straight alpha leaves a darkened color in translucent pixels, which shows up as dark
+    /// fringes around anti-aliased edges and text.
+    Premultiplied,
+    /// Premultiplied colors are added to the target, for glows and highlights.
+    Additive,
+    /// The target is multiplied by premultiplied colors, for shadows and tinting.
+    Multiply,
+}
+
+impl BlendMode {
+    /// Every mode, in the order of their pipelines in [`UiRenderer`].
+    const ALL: [BlendMode; 4] = [
+        BlendMode::Alpha,
+        BlendMode::Premultiplied,
+        BlendMode::Additive,
+        BlendMode::Multiply,
+    ];
+
+    /// Whether the fragment shader must premultiply its output by alpha.
+    fn premultiplied_output(self) -> bool {
+        self != BlendMode::Alpha
+    }
+
+    /// Color and alpha blend factors, as `(src, dst)` pairs.
+    fn blend_factors(self) -> [(BlendFactor, BlendFactor); 2] {
+        match self {
+            BlendMode::Alpha => [
+                (BlendFactor::SrcAlpha, BlendFactor::OneMinusSrcAlpha),
+                (BlendFactor::One, BlendFactor::OneMinusSrcAlpha),
+            ],
+            BlendMode::Premultiplied => [
+                (BlendFactor::One, BlendFactor::OneMinusSrcAlpha),
+                (BlendFactor::One, BlendFactor::OneMinusSrcAlpha),
+            ],
+            // Neither mode covers what is below, so the coverage of the target is kept.
+            BlendMode::Additive => [
+                (BlendFactor::One, BlendFactor::One),
+                (BlendFactor::Zero, BlendFactor::One),
+            ],
+            BlendMode::Multiply => [
+                (BlendFactor::DstColor, BlendFactor::OneMinusSrcAlpha),
+                (BlendFactor::Zero, BlendFactor::One),
+            ],
+        }
+    }
+
+    /// Color the UI layer is cleared to when multisampling, so that blending into it accumulates
+    /// what the mode does to the target: coverage for the alpha modes, the sum of colors for
+    /// additive and the product of factors for multiply.
+    fn layer_clear_color(self) -> ClearColor {
+        match self {
+            BlendMode::Multiply => ClearColor::RGBA(255, 255, 255, 255),
+            _ => ClearColor::RGBA(0, 0, 0, 0),
+        }
+    }
+
+    /// Color and alpha blend factors compositing the UI layer over the target, as `(src, dst)`
+    /// pairs, see [`BlendMode::layer_clear_color`].
+    fn layer_blend_factors(self) -> [(BlendFactor, BlendFactor); 2] {
+        match self {
+            // Blending into a transparent layer leaves premultiplied colors, even for straight
+            // alpha output.
+            BlendMode::Alpha | BlendMode::Premultiplied => [
+                (BlendFactor::One, BlendFactor::OneMinusSrcAlpha),
+                (BlendFactor::One, BlendFactor::OneMinusSrcAlpha),
+            ],
+            BlendMode::Additive => [
+                (BlendFactor::One, BlendFactor::One),
+                (BlendFactor::Zero, BlendFactor::One),
+            ],
+            BlendMode::Multiply => [
+                (BlendFactor::DstColor, BlendFactor::Zero),
+                (BlendFactor::Zero, BlendFactor::One),
+            ],
+        }
+    }
+}
+
+/// What a draw callback gets to record its own draws in the UI render pass.
+pub struct DrawCallbackContext<'a> {
+    pub device: &'a Device,
+    pub render_pass: &'a RenderPass,
+    pub command_buffer: &'a mut CommandBuffer,
+    /// Screen rectangle of the widget, in framebuffer pixels.
+    pub bounds: Rect<f32>,
+    /// Clip rectangle of the widget, in framebuffer pixels. The scissor is already set to it.
+    pub clip: Rect<f32>,
+}
+
+/// Callback drawing custom content in place of a widget, see [`UiRenderer::set_draw_callback`].
+pub type DrawCallback = Box<dyn FnMut(DrawCallbackContext<'_>)>;
+
+/// Where a draw callback runs in the current drawing context.
+struct CallbackPoint {
+    widget: Handle<UiNode>,
+    // Index of the command the callback runs after.
+    command: usize,
+    // Screen bounds of the widget, in UI units.
+    bounds: Rect<f32>,
+}
+
+/// Stencil usage of a UI pipeline.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum StencilMode {
+    /// Stencil is ignored.
+    Ignore,
+    /// Color writes are disabled and the stencil reference is written wherever geometry is drawn.
+    Write,
+    /// Only fragments where the stencil equals the reference are drawn.
+    Test,
+}
+
+/// Graphics pipeline created through `SDL_CreateGPUGraphicsPipeline`, as `GraphicsPipelineBuilder`
+/// cannot set the multisample state.
+///
+/// Like sdl3's own wrappers, it only holds a weak handle to the device, the renderer owns the
+/// device and drops it after its pipelines.
+#[derive(Clone)]
+struct Pipeline(Rc<RawPipeline>);
+
+struct RawPipeline {
+    device: Weak<Device>,
+    raw: *mut SDL_GPUGraphicsPipeline,
+}
+
+impl Pipeline {
+    fn new(device: &Rc<Device>, info: &SDL_GPUGraphicsPipelineCreateInfo) -> Result<Self, Error> {
+        let raw = unsafe { SDL_CreateGPUGraphicsPipeline(device.raw(), info) };
+        if raw.is_null() {
+            return Err(Error::Pipeline(sdl3::get_error()));
+        }
+
+        Ok(Self(Rc::new(RawPipeline {
+            device: Rc::downgrade(device),
+            raw,
+        })))
+    }
+
+    fn bind(&self, pass: &RenderPass) {
+        unsafe { SDL_BindGPUGraphicsPipeline(pass.raw(), self.0.raw) }
+    }
+}
+
+impl Drop for RawPipeline {
+    fn drop(&mut self) {
+        if let Some(device) = self.device.upgrade() {
+            unsafe { SDL_ReleaseGPUGraphicsPipeline(device.raw(), self.raw) }
+        }
+    }
+}
+
+/// Pipelines used to clip commands with clipping geometry.
+struct StencilPipelines {
+    write: Pipeline,
+    test: Pipeline,
+}
+
+/// Pipelines drawing with one blend mode.
+struct BlendPipelines {
+    pipeline: Pipeline,
+    // Only present when the renderer was configured with a depth-stencil format.
+    stencil_test: Option<Pipeline>,
+    // Only present when rendering with multisampling, see `UiRenderer::render_multisampled`.
+    composite: Option<GraphicsPipeline>,
+}
+
+/// Shaders of the UI pipelines, kept to rebuild them when the color format changes.
+struct Shaders {
+    vert: Shader,
+    frag: Shader,
+    // Fullscreen pass compositing the resolved UI layer over the target.
+    composite_vert: Shader,
+    composite_frag: Shader,
+}
+
+/// Textures the UI is drawn into when multisampling, before being composited over the target.
+#[derive(Clone)]
+struct MultisampleTargets {
+    samples: Texture<'static>,
+    // Single sample texture the samples are resolved into.
+    resolved: Texture<'static>,
+}
 
 /// GPU resources for the UI render pass.
 pub struct UiRenderer {
-    pub pipeline: GraphicsPipeline,
+    pipeline: Pipeline,
+    // Only present when the renderer was configured with a depth-stencil format.
+    stencil: Option<StencilPipelines>,
+    // Pipelines of every blend mode, in the order of `BlendMode::ALL`. `pipeline` and the stencil
+    // test pipeline are clones of the ones of `blend_mode`.
+    blend_pipelines: Vec<BlendPipelines>,
+    blend_mode: BlendMode,
+    shaders: Shaders,
+    // Format of the color targets the pipelines were built for.
+    format: TextureFormat,
+    config: UiRendererConfig,
+    // Sample count the pipelines were built for, after the fallback.
+    sample_count: SampleCount,
+    // Layer the UI is drawn into when multisampling, sized to the last render.
+    multisample_targets: Option<MultisampleTargets>,
     sampler_linear: Sampler,
     // Cached white 1×1 for fallback.
     white_tex: Texture<'static>,
+    // Uploaded font atlas pages, keyed by font resource.
+    font_pages: FontPageCache<Texture<'static>>,
+    // Uploaded user textures, keyed by texture resource.
+    textures: TextureCache<TextureCacheEntry>,
+    // Application GPU textures, keyed by the texture resource they were registered under.
+    external_textures: ResourceCache<TextureResource, ExternalTexture>,
+    // Geometry buffers reused across frames, grown on demand.
+    vertex_buffer: Option<Buffer>,
+    index_buffer: Option<Buffer>,
+    // Staging buffer of the geometry and texture uploads, grown on demand.
+    upload_buffer: Option<TransferBuffer>,
+    // Scratch storage for flattened triangle indices.
+    indices: Vec<u32>,
+    // Triangle ranges of the uploaded clipping geometry, in the order of the clipped commands.
+    clip_ranges: Vec<Range<usize>>,
+    draw_callbacks: HashMap<Handle<UiNode>, DrawCallback>,
+    // Callbacks located in the current drawing context, sorted by command.
+    callback_points: Vec<CallbackPoint>,
+    stats: RenderStats,
+    // Kept alive until the pipelines, which only refer to it weakly, are released, so it must
+    // stay the last field.
+    device: Rc<Device>,
 }
 
 impl UiRenderer {
+    /// Create a renderer for the swapchain of `window`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if GPU resources cannot be created, see [`UiRenderer::try_new`].
     pub fn new(device: &Device, window: &Window) -> Self {
-        let format = device.get_swapchain_texture_format(window);
+        Self::try_new(device, window).expect("failed to create UI renderer")
+    }
+
+    /// Create a renderer for the swapchain of `window`, reporting failures instead of panicking.
+    pub fn try_new(device: &Device, window: &Window) -> Result<Self, Error> {
+        Self::with_config(device, window, UiRendererConfig::default())
+    }
+
+    /// Create a renderer with the given options, see [`UiRendererConfig`].
+    pub fn with_config(device: &Device, window: &Window, config: UiRendererConfig) -> Result<Self, Error> {
+        Self::with_format(device, device.get_swapchain_texture_format(window), config)
+    }
+
+    /// Create a renderer for color targets of the given format, independent of any window.
+    ///
+    /// Use this with [`UiRenderer::render_to_target`] to draw the UI into your own textures.
+    pub fn with_format(device: &Device, format: TextureFormat, config: UiRendererConfig) -> Result<Self, Error> {
+        // Rejected before creating any GPU resources.
+        if config.composition == SwapchainComposition::Hdr10St2084 {
+            return Err(Error::UnsupportedComposition(config.composition));
+        }
 
         let vert = device
             .create_shader()
@@ -31,7 +585,7 @@ impl UiRenderer {
             .with_uniform_buffers(1)
             .with_entrypoint(c"main")
             .build()
-            .unwrap();
+            .map_err(Error::Shader)?;
 
         let frag = device
             .create_shader()
@@ -44,59 +598,47 @@ impl UiRenderer {
             .with_uniform_buffers(1)
             .with_entrypoint(c"main")
             .build()
-            .unwrap();
+            .map_err(Error::Shader)?;
 
-        let pipeline = device
-            .create_graphics_pipeline()
-            .with_vertex_shader(&vert)
-            .with_fragment_shader(&frag)
-            .with_vertex_input_state(
-                VertexInputState::new()
-                    .with_vertex_buffer_descriptions(&[VertexBufferDescription::new()
-                        .with_slot(0)
-                        .with_pitch(std::mem::size_of::<fyrox_ui::draw::Vertex>() as u32)
-                        .with_input_rate(VertexInputRate::Vertex)
-                        .with_instance_step_rate(0)])
-                    .with_vertex_attributes(&[
-                        VertexAttribute::new()
-                            .with_format(VertexElementFormat::Float2)
-                            .with_location(0)
-                            .with_buffer_slot(0)
-                            .with_offset(offset_of!(fyrox_ui::draw::Vertex, pos) as u32),
-                        VertexAttribute::new()
-                            .with_format(VertexElementFormat::Float2)
-                            .with_location(1)
-                            .with_buffer_slot(0)
-                            .with_offset(offset_of!(fyrox_ui::draw::Vertex, tex_coord) as u32),
-                        VertexAttribute::new()
-                            .with_format(VertexElementFormat::Ubyte4Norm)
-                            .with_location(2)
-                            .with_buffer_slot(0)
-                            .with_offset(offset_of!(fyrox_ui::draw::Vertex, color) as u32),
-                    ]),
-            )
-            .with_rasterizer_state(
-                RasterizerState::new()
-                    .with_fill_mode(FillMode::Fill)
-                    .with_front_face(FrontFace::Clockwise), // Disable culling for UI geometry
+        let composite_vert = device
+            .create_shader()
+            .with_code(
+                ShaderFormat::SPIRV,
+                include_bytes!(concat!(env!("OUT_DIR"), "/composite.vert.spv")),
+                ShaderStage::Vertex,
             )
-            .with_primitive_type(PrimitiveType::TriangleList)
-            .with_target_info(
-                GraphicsPipelineTargetInfo::new().with_color_target_descriptions(&[ColorTargetDescription::new()
-                    .with_format(format)
-                    .with_blend_state(
-                        ColorTargetBlendState::new()
-                            .with_color_blend_op(BlendOp::Add)
-                            .with_src_color_blendfactor(BlendFactor::SrcAlpha)
-                            .with_dst_color_blendfactor(BlendFactor::OneMinusSrcAlpha)
-                            .with_alpha_blend_op(BlendOp::Add)
-                            .with_src_alpha_blendfactor(BlendFactor::One)
-                            .with_dst_alpha_blendfactor(BlendFactor::OneMinusSrcAlpha)
-                            .with_enable_blend(true),
-                    )]),
+            .with_entrypoint(c"main")
+            .build()
+            .map_err(Error::Shader)?;
+
+        let composite_frag = device
+            .create_shader()
+            .with_code(
+                ShaderFormat::SPIRV,
+                include_bytes!(concat!(env!("OUT_DIR"), "/composite.frag.spv")),
+                ShaderStage::Fragment,
             )
+            .with_samplers(1)
+            .with_entrypoint(c"main")
             .build()
-            .unwrap();
+            .map_err(Error::Shader)?;
+
+        let shaders = Shaders {
+            vert,
+            frag,
+            composite_vert,
+            composite_frag,
+        };
+
+        let sample_count = supported_sample_count(device, format, config.depth_stencil_format, config.sample_count);
+        let targets = PipelineTargets {
+            format,
+            depth_stencil_format: config.depth_stencil_format,
+            sample_count,
+        };
+        let blend_mode = config.blend_mode;
+        let shared_device = Rc::new(device.clone());
+        let (pipeline, stencil, blend_pipelines) = create_pipelines(&shared_device, &shaders, &targets, blend_mode)?;
 
         let sampler_linear = device
             .create_sampler(
@@ -106,85 +648,624 @@ impl UiRenderer {
                     .with_mipmap_mode(SamplerMipmapMode::Linear)
                     .with_address_mode_u(SamplerAddressMode::ClampToEdge)
                     .with_address_mode_v(SamplerAddressMode::ClampToEdge)
-                    .with_address_mode_w(SamplerAddressMode::ClampToEdge),
+                    .with_address_mode_w(SamplerAddressMode::ClampToEdge)
+                    .with_max_lod(1000.0),
             )
-            .unwrap();
+            .map_err(Error::Sampler)?;
 
-        // 1×1 white fallback
+        // 1×1 white fallback, RGBA as A8 textures sample as (0, 0, 0, a) and textured commands
+        // multiply every channel.
+        let mut upload_buffer = None;
         let white_tex = {
-            let copy_cmds = device.acquire_command_buffer().unwrap();
-            let copy_pass = device.begin_copy_pass(&copy_cmds).unwrap();
-            let tex = create_texture(device, &copy_pass, &[255], 1, 1).unwrap();
+            let mut copy_cmds = device.acquire_command_buffer().map_err(Error::Command)?;
+            let copy_pass = match device.begin_copy_pass(&copy_cmds) {
+                Ok(copy_pass) => copy_pass,
+                Err(err) => {
+                    copy_cmds.cancel();
+                    return Err(Error::Command(err));
+                }
+            };
+            let tex = create_texture_with_format(device, TextureFormat::R8g8b8a8Unorm, 1, 1, 1).and_then(|tex| {
+                update_texture_level(device, &copy_pass, &mut upload_buffer, &tex, 0, &[255; 4], 1, 1, false)?;
+                Ok(tex)
+            });
             device.end_copy_pass(copy_pass);
-            copy_cmds.submit().unwrap();
-            tex
+            match tex {
+                Ok(tex) => {
+                    copy_cmds.submit().map_err(Error::Command)?;
+                    tex
+                }
+                Err(err) => {
+                    copy_cmds.cancel();
+                    return Err(Error::Upload(err));
+                }
+            }
         };
 
-        Self {
+        Ok(Self {
             pipeline,
+            stencil,
+            blend_pipelines,
+            blend_mode,
+            shaders,
+            format,
+            config,
+            sample_count,
+            multisample_targets: None,
             sampler_linear,
             white_tex,
+            font_pages: ResourceCache::new(),
+            textures: ResourceCache::new(),
+            external_textures: ResourceCache::new(),
+            vertex_buffer: None,
+            index_buffer: None,
+            upload_buffer,
+            indices: Vec::new(),
+            clip_ranges: Vec::new(),
+            draw_callbacks: HashMap::new(),
+            callback_points: Vec::new(),
+            stats: RenderStats::default(),
+            device: shared_device,
+        })
+    }
+
+    /// Format of the color targets the renderer draws to.
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    /// Create a `width`×`height` target of the renderer's format, to render the UI into and
+    /// download it with [`submit_with_readback`](crate::readback::submit_with_readback).
+    ///
+    /// The target keeps the format it was created with, so it must be recreated after
+    /// [`UiRenderer::set_format`].
+    pub fn create_readback_target(&self, device: &Device, width: u32, height: u32) -> Result<ReadbackTarget, Error> {
+        ReadbackTarget::new(device, self.format, width, height)
+    }
+
+    /// Options the renderer was created with.
+    pub fn config(&self) -> &UiRendererConfig {
+        &self.config
+    }
+
+    /// Sample count the UI is rendered with, which can be lower than the configured one if the
+    /// target formats do not support it.
+    ///
+    /// Render passes given to [`PreparedFrame::draw`] must have targets with this sample count.
+    pub fn sample_count(&self) -> SampleCount {
+        self.sample_count
+    }
+
+    /// Rebuild the pipelines for color targets of `format`.
+    ///
+    /// [`UiRenderer::render`] does it on its own when the swapchain format changes, e.g. after
+    /// switching its composition. Call it before [`UiRenderer::prepare`] or
+    /// [`UiRenderer::render_to_target`] when the targets change format. `device` must be the one
+    /// the renderer was created with.
+    pub fn set_format(&mut self, device: &Device, format: TextureFormat) -> Result<(), Error> {
+        let sample_count = supported_sample_count(
+            device,
+            format,
+            self.config.depth_stencil_format,
+            self.config.sample_count,
+        );
+        let targets = PipelineTargets {
+            format,
+            depth_stencil_format: self.config.depth_stencil_format,
+            sample_count,
+        };
+        (self.pipeline, self.stencil, self.blend_pipelines) =
+            create_pipelines(&self.device, &self.shaders, &targets, self.blend_mode)?;
+        self.format = format;
+        self.sample_count = sample_count;
+        // Recreated with the new format on the next render.
+        self.multisample_targets = None;
+
+        Ok(())
+    }
+
+    /// Encode the output for a swapchain set to `composition`, see
+    /// [`UiRendererConfig::composition`].
+    ///
+    /// The color format usually changes along with the composition, which the next
+    /// [`UiRenderer::render`] picks up.
+    pub fn set_composition(&mut self, composition: SwapchainComposition) -> Result<(), Error> {
+        if composition == SwapchainComposition::Hdr10St2084 {
+            return Err(Error::UnsupportedComposition(composition));
+        }
+        self.config.composition = composition;
+        Ok(())
+    }
+
+    /// Set the luminance of UI white in nits on `HdrExtendedLinear` swapchains.
+    pub fn set_hdr_brightness(&mut self, nits: f32) {
+        self.config.hdr_brightness = nits;
+    }
+
+    /// Output encoding of the fragment shader for the current target and blend mode.
+    fn fragment_output(&self) -> FragmentOutput {
+        FragmentOutput::new(
+            self.format,
+            self.config.composition,
+            self.config.hdr_brightness,
+            self.blend_mode,
+        )
+    }
+
+    /// Pipeline drawing the UI with the current blend mode and without stencil clipping.
+    ///
+    /// This replaces the former public `pipeline` field. The pipeline has the sample count of
+    /// [`UiRenderer::sample_count`], which `GraphicsPipelineBuilder` cannot set, so it is created
+    /// through SDL directly and only exposed as a raw handle, owned by the renderer.
+    pub fn raw_pipeline(&self) -> *mut SDL_GPUGraphicsPipeline {
+        self.pipeline.0.raw
+    }
+
+    /// Blend mode the UI is currently composited with.
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// Composite the UI with `mode` from the next render on.
+    ///
+    /// Pipelines of every mode are created with the renderer, so this is cheap enough to be
+    /// called every frame, e.g. when rendering the same UI to the swapchain and to a texture.
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        let pipelines = &self.blend_pipelines[mode as usize];
+        self.pipeline = pipelines.pipeline.clone();
+        if let (Some(stencil), Some(test)) = (&mut self.stencil, &pipelines.stencil_test) {
+            stencil.test = test.clone();
         }
+        self.blend_mode = mode;
+    }
+
+    /// Statistics of the last rendered frame.
+    pub fn stats(&self) -> RenderStats {
+        self.stats
+    }
+
+    /// Make an application owned GPU texture drawable by the UI.
+    ///
+    /// Returns a texture resource to use in `Image` widgets or brushes, commands referring to it
+    /// sample `texture` directly, so whatever was rendered into it earlier in the frame shows up
+    /// without any CPU copy. The texture needs the `SAMPLER` usage. It is released once both
+    /// the application and the UI have dropped the resource, or by
+    /// [`UiRenderer::unregister_texture`].
+    ///
+    /// `format` is the one the texture was created with, SDL cannot tell it. sRGB and float
+    /// textures are sampled as linear values, other formats are taken as sRGB encoded like the
+    /// textures of fyrox.
+    pub fn register_texture(&mut self, texture: Texture<'static>, format: TextureFormat) -> TextureResource {
+        let resource = TextureResource::new_render_target(texture.width(), texture.height());
+        self.external_textures.insert(
+            &resource,
+            ExternalTexture {
+                texture,
+                linear: is_linear_format(format),
+            },
+        );
+        resource
+    }
+
+    /// Show `texture`, created with `format`, in place of the one registered under `resource`,
+    /// e.g. after recreating it at a new size. Returns the previous texture, or `None` if
+    /// `resource` was not registered.
+    pub fn replace_texture(
+        &mut self,
+        resource: &TextureResource,
+        texture: Texture<'static>,
+        format: TextureFormat,
+    ) -> Option<Texture<'static>> {
+        let entry = self.external_textures.get_mut(resource)?;
+        entry.linear = is_linear_format(format);
+        Some(std::mem::replace(&mut entry.texture, texture))
+    }
+
+    /// Stop showing the texture registered under `resource`, commands referring to it are drawn
+    /// untextured afterwards.
+    pub fn unregister_texture(&mut self, resource: &TextureResource) -> Option<Texture<'static>> {
+        self.external_textures.remove(resource).map(|entry| entry.texture)
+    }
+
+    /// Run `callback` right after the commands `widget` draws itself with, before its children,
+    /// replacing any previous callback of the widget.
+    ///
+    /// This embeds custom GPU content, like 3D previews or video frames, in the UI draw order:
+    /// the widget background is beneath, children and widgets drawn later are on top. The
+    /// content is clipped to the widget clip bounds, clipping geometry is not applied. The
+    /// callback is skipped on frames the widget does not draw anything, e.g. when it is hidden.
+    ///
+    /// Callbacks are located with [`UiRenderer::locate_draw_callbacks`] before each render, frames
+    /// drawn without locating them skip the callbacks.
+    pub fn set_draw_callback<F>(&mut self, widget: Handle<UiNode>, callback: F)
+    where
+        F: FnMut(DrawCallbackContext<'_>) + 'static,
+    {
+        self.draw_callbacks.insert(widget, Box::new(callback));
+    }
+
+    /// Remove the draw callback of `widget`, returning it if there was one.
+    pub fn remove_draw_callback(&mut self, widget: Handle<UiNode>) -> Option<DrawCallback> {
+        self.draw_callbacks.remove(&widget)
+    }
+
+    /// Find where the widgets with a draw callback are in the drawing context of `ui`.
+    ///
+    /// Must be called after `UserInterface::draw` and before rendering its drawing context. The
+    /// located points are only used by the next [`PreparedFrame::draw`] or render, so indices of a
+    /// previous frame never apply to a new one. [`FyroxUiSdl`](crate::FyroxUiSdl) does it on its
+    /// own.
+    pub fn locate_draw_callbacks(&mut self, ui: &UserInterface) {
+        self.callback_points.clear();
+        for &widget in self.draw_callbacks.keys() {
+            let Some(node) = ui.nodes().try_borrow(widget) else {
+                continue;
+            };
+
+            // Indices are in draw order, the first contiguous run is what the widget draws before
+            // its children.
+            let indices = node.command_indices.borrow();
+            let Some(&first) = indices.first() else {
+                continue;
+            };
+            let command = indices
+                .iter()
+                .zip(first..)
+                .take_while(|&(&index, expected)| index == expected)
+                .last()
+                .map_or(first, |(&index, _)| index);
+
+            self.callback_points.push(CallbackPoint {
+                widget,
+                command,
+                bounds: node.screen_bounds(),
+            });
+        }
+        self.callback_points.sort_by_key(|point| point.command);
     }
 
     /// Render Fyrox-UI `DrawingContext` to the current color target(s).
     ///
     /// `color_targets` must be the same swapchain target you used for the rest of your frame.
+    /// Texture and geometry uploads are recorded in a copy pass on `command_buffer` right before
+    /// the UI render pass, nothing is submitted on its own.
+    ///
+    /// `depth_stencil_target` must be given if, and only if, the renderer was configured with a
+    /// [`UiRendererConfig::depth_stencil_format`]. Its stencil is expected to be cleared to zero
+    /// and is left that way.
     pub fn render(
-        &self,
+        &mut self,
         device: &sdl3::gpu::Device,
         window: &sdl3::video::Window,
         command_buffer: &mut CommandBuffer,
         color_targets: &[ColorTargetInfo],
+        depth_stencil_target: Option<&DepthStencilTargetInfo>,
         drawing: &DrawingContext,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let (width, height) = window.size();
-        let vertices_len = drawing.get_vertices().len();
+    ) -> Result<(), Error> {
+        let format = device.get_swapchain_texture_format(window);
+        if format != self.format {
+            self.set_format(device, format)?;
+        }
+
+        let scale = WindowScale::of(window);
+        self.render_scaled(
+            device,
+            command_buffer,
+            color_targets,
+            depth_stencil_target,
+            &scale,
+            drawing,
+        )
+    }
+
+    /// Render Fyrox-UI `DrawingContext` to color target(s) of `width`×`height` pixels, independent
+    /// of any window.
+    ///
+    /// `color_targets` can be any textures created with the format given to
+    /// [`UiRenderer::with_format`] and the `COLOR_TARGET` usage, and `SAMPLER` if the result is
+    /// sampled later. Their load op decides whether the UI is drawn over the previous content or
+    /// a cleared texture. One UI unit maps to one pixel, so the UI should be laid out at the same
+    /// size. Uploads and depth-stencil targets are handled as in [`UiRenderer::render`].
+    pub fn render_to_target(
+        &mut self,
+        device: &Device,
+        command_buffer: &mut CommandBuffer,
+        color_targets: &[ColorTargetInfo],
+        depth_stencil_target: Option<&DepthStencilTargetInfo>,
+        (width, height): (u32, u32),
+        drawing: &DrawingContext,
+    ) -> Result<(), Error> {
+        let scale = WindowScale {
+            size_in_pixels: (width, height),
+            ..WindowScale::default()
+        };
+        self.render_scaled(
+            device,
+            command_buffer,
+            color_targets,
+            depth_stencil_target,
+            &scale,
+            drawing,
+        )
+    }
+
+    fn render_scaled(
+        &mut self,
+        device: &Device,
+        command_buffer: &mut CommandBuffer,
+        color_targets: &[ColorTargetInfo],
+        depth_stencil_target: Option<&DepthStencilTargetInfo>,
+        scale: &WindowScale,
+        drawing: &DrawingContext,
+    ) -> Result<(), Error> {
+        let required = self.config.depth_stencil_format.is_some();
+        if required != depth_stencil_target.is_some() {
+            return Err(Error::DepthStencilTarget { required });
+        }
 
         // Skip rendering if there's nothing to draw
-        if width == 0 || height == 0 || vertices_len == 0 {
+        self.stats = RenderStats::default();
+        let (width, height) = scale.size_in_pixels;
+        if width == 0 || height == 0 || is_empty(drawing) {
             return Ok(());
         }
 
-        let (vbuf, vidx) = {
-            let copy_commands = device.acquire_command_buffer()?;
-            let transfer_buffer = device
-                .create_transfer_buffer()
-                .with_size(std::mem::size_of_val(drawing.get_vertices()) as u32)
-                .with_usage(sdl3::gpu::TransferBufferUsage::UPLOAD)
-                .build()?;
-            let copy_pass = device.begin_copy_pass(&copy_commands)?;
-            let vbuf = crate::utils::create_buffer_with_data(
-                device,
-                &transfer_buffer,
-                &copy_pass,
-                BufferUsageFlags::VERTEX,
-                drawing.get_vertices(),
-            )?;
-            let indices: Vec<u32> = drawing
-                .get_triangles()
-                .iter()
-                .flat_map(|tri| tri.indices()) // flattens &[u32] into u32
-                .copied() // &[u32] → u32
-                .collect();
-            let vidx = crate::utils::create_buffer_with_data(
+        self.upload(device, command_buffer, drawing)?;
+
+        if self.sample_count != SampleCount::NoMultiSampling {
+            return self.render_multisampled(
                 device,
-                &transfer_buffer,
-                &copy_pass,
-                BufferUsageFlags::INDEX,
-                &indices,
-            )?;
+                command_buffer,
+                color_targets,
+                depth_stencil_target,
+                scale,
+                drawing,
+            );
+        }
+
+        let render_pass = device
+            .begin_render_pass(command_buffer, color_targets, depth_stencil_target)
+            .map_err(Error::Command)?;
+        let drawn = self.draw(device, command_buffer, &render_pass, scale, drawing);
+        device.end_render_pass(render_pass);
+
+        drawn
+    }
+
+    /// Draw the prepared UI into a multisample layer, resolve it, and composite the result over
+    /// the color target with the current blend mode.
+    ///
+    /// Going through a layer keeps the previous content of the target when it is loaded, which
+    /// resolving straight into the target would overwrite.
+    fn render_multisampled(
+        &mut self,
+        device: &Device,
+        command_buffer: &mut CommandBuffer,
+        color_targets: &[ColorTargetInfo],
+        depth_stencil_target: Option<&DepthStencilTargetInfo>,
+        scale: &WindowScale,
+        drawing: &DrawingContext,
+    ) -> Result<(), Error> {
+        if color_targets.len() != 1 {
+            return Err(Error::MultisampleTarget);
+        }
+
+        let (width, height) = scale.size_in_pixels;
+        // Cloned, as drawing borrows the whole renderer.
+        let targets = match &mut self.multisample_targets {
+            Some(targets) if targets.resolved.width() == width && targets.resolved.height() == height => targets,
+            targets => targets.insert(MultisampleTargets {
+                samples: create_multisample_target(device, self.format, width, height, self.sample_count)
+                    .map_err(Error::Upload)?,
+                resolved: create_layer_target(device, self.format, width, height).map_err(Error::Upload)?,
+            }),
+        }
+        .clone();
+
+        let layer = [ColorTargetInfo::default()
+            .with_texture(&targets.samples)
+            .with_load_op(LoadOp::CLEAR)
+            .with_store_op(StoreOp::STORE)
+            .with_clear_color(self.blend_mode.layer_clear_color())];
+        let render_pass = device
+            .begin_render_pass(command_buffer, &layer, depth_stencil_target)
+            .map_err(Error::Command)?;
+        let drawn = self.draw(device, command_buffer, &render_pass, scale, drawing);
+        device.end_render_pass(render_pass);
+        drawn?;
+
+        resolve(command_buffer, &targets.samples, &targets.resolved)?;
+
+        let Some(composite) = &self.blend_pipelines[self.blend_mode as usize].composite else {
+            return Err(Error::CompositePipeline);
+        };
+        let render_pass = device
+            .begin_render_pass(command_buffer, color_targets, None)
+            .map_err(Error::Command)?;
+        render_pass.bind_graphics_pipeline(composite);
+        device.set_viewport(
+            &render_pass,
+            SDL_GPUViewport {
+                x: 0.0,
+                y: 0.0,
+                w: width as f32,
+                h: height as f32,
+                min_depth: 0.0,
+                max_depth: 1.0,
+            },
+        );
+        render_pass.bind_fragment_samplers(
+            0,
+            &[TextureSamplerBinding::new()
+                .with_texture(&targets.resolved)
+                .with_sampler(&self.sampler_linear)],
+        );
+        render_pass.draw_primitives(3, 1, 0, 0);
+        device.end_render_pass(render_pass);
+
+        Ok(())
+    }
+
+    /// Upload the textures and geometry of `drawing`, to draw it into a render pass of your own
+    /// with [`PreparedFrame::draw`].
+    ///
+    /// Uploads are recorded in a copy pass on `command_buffer`, so this must be called while no
+    /// render pass is open, typically before beginning the pass the UI is drawn in.
+    pub fn prepare<'a>(
+        &'a mut self,
+        device: &Device,
+        command_buffer: &CommandBuffer,
+        drawing: &'a DrawingContext,
+    ) -> Result<PreparedFrame<'a>, Error> {
+        self.upload(device, command_buffer, drawing)?;
+        Ok(PreparedFrame {
+            renderer: self,
+            drawing,
+        })
+    }
+
+    /// Upload the textures and geometry of `drawing`, ahead of drawing it.
+    fn upload(
+        &mut self,
+        device: &Device,
+        command_buffer: &CommandBuffer,
+        drawing: &DrawingContext,
+    ) -> Result<(), Error> {
+        if is_empty(drawing) {
+            return Ok(());
+        }
+
+        // Record every upload in a single copy pass, so the whole UI frame goes out with the
+        // caller's submission.
+        let mut upload = None;
+        let uploaded = self
+            .update_textures(device, command_buffer, &mut upload, drawing)
+            .and_then(|_| self.upload_geometry(device, command_buffer, &mut upload, drawing));
+        if let Some(copy_pass) = upload {
             device.end_copy_pass(copy_pass);
-            copy_commands.submit()?;
-            (vbuf, vidx)
+        }
+        uploaded
+    }
+
+    /// Record the draws of `drawing`, which was just uploaded, into `render_pass`, see
+    /// [`PreparedFrame::draw`].
+    fn draw(
+        &mut self,
+        device: &Device,
+        command_buffer: &mut CommandBuffer,
+        render_pass: &RenderPass,
+        scale: &WindowScale,
+        drawing: &DrawingContext,
+    ) -> Result<(), Error> {
+        // Render at the full framebuffer resolution, the UI itself is laid out in UI units.
+        let (width, height) = scale.size_in_pixels;
+        self.stats = RenderStats::default();
+        // Command indices are only valid for the context they were located in.
+        let callback_points = std::mem::take(&mut self.callback_points);
+
+        if width == 0 || height == 0 || is_empty(drawing) {
+            return Ok(());
+        }
+
+        let (Some(vbuf), Some(vidx)) = (&self.vertex_buffer, &self.index_buffer) else {
+            return Ok(());
+        };
+
+        self.bind_pass_state(device, command_buffer, render_pass, vbuf, vidx, scale);
+
+        // Draw every run of compatible commands with a single draw call
+        let commands = drawing.get_commands();
+        let mut state = DrawState::default();
+        let mut stats = RenderStats {
+            commands: commands.len(),
+            draw_calls: 0,
         };
+        let mut clip_ranges = self.clip_ranges.iter();
+        let mut start = 0;
+        // Draw callbacks split the commands into segments, batches never cross them.
+        for point in callback_points.iter().map(Some).chain([None]) {
+            let end = point.map_or(commands.len(), |point| point.command + 1);
+            let end = end.clamp(start, commands.len());
+            for (cmd_ui, triangles) in batches(&commands[start..end]) {
+                // Clipped commands are never merged, so each one takes the next clip range.
+                let clip = match (&self.stencil, &cmd_ui.clipping_geometry) {
+                    (Some(stencil), Some(_)) => clip_ranges.next().map(|range| (stencil, range.clone())),
+                    _ => None,
+                };
+
+                let Some((stencil, clip)) = clip else {
+                    self.draw_batch(render_pass, command_buffer, &mut state, cmd_ui, triangles, scale);
+                    stats.draw_calls += 1;
+                    continue;
+                };
+
+                // Mark the clipping geometry in the stencil, draw the command where it is marked,
+                // then clear the mark again so the stencil is back to zero for the next clipped
+                // command.
+                stencil.write.bind(render_pass);
+                set_stencil_reference(render_pass, 1);
+                self.draw_batch(render_pass, command_buffer, &mut state, cmd_ui, clip.clone(), scale);
+                stencil.test.bind(render_pass);
+                self.draw_batch(render_pass, command_buffer, &mut state, cmd_ui, triangles, scale);
+                stencil.write.bind(render_pass);
+                set_stencil_reference(render_pass, 0);
+                self.draw_batch(render_pass, command_buffer, &mut state, cmd_ui, clip, scale);
+                self.pipeline.bind(render_pass);
+                stats.draw_calls += 3;
+            }
+            start = end;
 
-        let render_pass = device.begin_render_pass(command_buffer, color_targets, None)?;
-        render_pass.bind_graphics_pipeline(&self.pipeline);
+            let Some(point) = point else {
+                break;
+            };
+            let (Some(callback), Some(cmd_ui)) =
+                (self.draw_callbacks.get_mut(&point.widget), commands.get(point.command))
+            else {
+                continue;
+            };
+
+            let clip = scissor_rect(&cmd_ui.clip_bounds, scale);
+            set_scissor(render_pass, clip);
+            callback(DrawCallbackContext {
+                device,
+                render_pass,
+                command_buffer,
+                bounds: Rect::new(
+                    point.bounds.position.x * scale.display_scale,
+                    point.bounds.position.y * scale.display_scale,
+                    point.bounds.size.x * scale.display_scale,
+                    point.bounds.size.y * scale.display_scale,
+                ),
+                clip: Rect::new(clip.0 as f32, clip.1 as f32, clip.2 as f32, clip.3 as f32),
+            });
+
+            // The callback may have changed any state of the pass.
+            self.bind_pass_state(device, command_buffer, render_pass, vbuf, vidx, scale);
+            state = DrawState::default();
+        }
+        self.stats = stats;
+
+        Ok(())
+    }
+
+    /// Bind the UI pipeline and geometry, and set the viewport and projection for `scale`.
+    fn bind_pass_state(
+        &self,
+        device: &Device,
+        command_buffer: &CommandBuffer,
+        render_pass: &RenderPass,
+        vbuf: &Buffer,
+        vidx: &Buffer,
+        scale: &WindowScale,
+    ) {
+        let (width, height) = scale.size_in_pixels;
+        let ui_size = scale.ui_size();
+
+        self.pipeline.bind(render_pass);
 
         // Set viewport and projection matrix
         device.set_viewport(
-            &render_pass,
+            render_pass,
             SDL_GPUViewport {
                 x: 0.0,
                 y: 0.0,
@@ -197,167 +1278,883 @@ impl UiRenderer {
 
         // Push orthographic projection matrix
         let matrix = [
-            [2.0 / width as f32, 0.0, 0.0, 0.0],
-            [0.0, 2.0 / -(height as f32), 0.0, 0.0],
+            [2.0 / ui_size.x, 0.0, 0.0, 0.0],
+            [0.0, 2.0 / -ui_size.y, 0.0, 0.0],
             [0.0, 0.0, -1.0, 0.0],
             [-1.0, 1.0, 0.0, 1.0],
         ];
         command_buffer.push_vertex_uniform_data(0, &matrix);
 
-        render_pass.bind_vertex_buffers(0, &[BufferBinding::new().with_buffer(&vbuf).with_offset(0)]);
+        render_pass.bind_vertex_buffers(0, &[BufferBinding::new().with_buffer(vbuf).with_offset(0)]);
         render_pass.bind_index_buffer(
-            &BufferBinding::new().with_buffer(&vidx).with_offset(0),
+            &BufferBinding::new().with_buffer(vidx).with_offset(0),
             IndexElementSize::_32BIT,
         );
+    }
+
+    /// Upload the font atlas pages and user textures referenced by `drawing` that are new or were
+    /// modified since the last upload, and drop the ones that are no longer used by the UI.
+    fn update_textures(
+        &mut self,
+        device: &Device,
+        command_buffer: &CommandBuffer,
+        upload: &mut UploadPass,
+        drawing: &DrawingContext,
+    ) -> Result<(), Error> {
+        self.font_pages.evict_unused();
+        self.textures.evict_unused();
+        self.external_textures.evict_unused();
 
-        // Draw every command
         for cmd_ui in drawing.get_commands() {
-            self.draw_command(device, &render_pass, command_buffer, cmd_ui, (width, height))?;
+            match &cmd_ui.texture {
+                CommandTexture::Font {
+                    font,
+                    page_index,
+                    height,
+                } => self.update_font_page(device, command_buffer, upload, font, height, *page_index)?,
+                CommandTexture::Texture(texture) => self.update_texture(device, command_buffer, upload, texture)?,
+                CommandTexture::None => {}
+            }
         }
 
-        // render_pass.draw_primitives(vertices_len, 1, 0, 0);
+        Ok(())
+    }
 
-        device.end_render_pass(render_pass);
+    /// Upload the vertices and indices of `drawing` into the persistent geometry buffers.
+    ///
+    /// All the data goes through a single upload transfer buffer. Both the transfer buffer and the
+    /// GPU buffers are cycled, so SDL hands us fresh memory while the previous frame is still in
+    /// flight, and they are only reallocated when the draw data outgrows them.
+    fn upload_geometry(
+        &mut self,
+        device: &Device,
+        command_buffer: &CommandBuffer,
+        upload: &mut UploadPass,
+        drawing: &DrawingContext,
+    ) -> Result<(), Error> {
+        self.indices.clear();
+        self.indices
+            .extend(drawing.get_triangles().iter().flat_map(|tri| tri.indices()).copied());
+
+        // Clipping geometry goes after the UI geometry so the stencil pass can draw it from the
+        // same buffers. Without a stencil target it is never drawn, so it isn't uploaded either.
+        self.clip_ranges.clear();
+        let mut clip_vertices = Vec::new();
+        if self.stencil.is_some() {
+            let mut base_vertex = drawing.get_vertices().len() as u32;
+            for geometry in drawing
+                .get_commands()
+                .iter()
+                .filter_map(|cmd| cmd.clipping_geometry.as_ref())
+            {
+                let start = self.indices.len() / 3;
+                self.indices.extend(
+                    geometry
+                        .triangle_buffer
+                        .iter()
+                        .flat_map(|tri| tri.indices())
+                        .map(|index| index + base_vertex),
+                );
+                self.clip_ranges.push(start..self.indices.len() / 3);
+                // SAFETY: `Vertex` is `Pod`.
+                clip_vertices.push(unsafe { as_bytes(&geometry.vertex_buffer) });
+                base_vertex += geometry.vertex_buffer.len() as u32;
+            }
+        }
+
+        // SAFETY: `Vertex` is `Pod` and indices are plain integers.
+        let ui_vertex_bytes = unsafe { as_bytes(drawing.get_vertices()) };
+        let index_bytes = unsafe { as_bytes(&self.indices) };
+        let vertex_len = (ui_vertex_bytes.len() + clip_vertices.iter().map(|bytes| bytes.len()).sum::<usize>()) as u32;
+        let index_len = index_bytes.len() as u32;
+
+        let vertex_buffer = ensure_buffer(device, &mut self.vertex_buffer, BufferUsageFlags::VERTEX, vertex_len)
+            .map_err(Error::Upload)?;
+        let index_buffer =
+            ensure_buffer(device, &mut self.index_buffer, BufferUsageFlags::INDEX, index_len).map_err(Error::Upload)?;
+        let transfer_buffer =
+            ensure_transfer_buffer(device, &mut self.upload_buffer, vertex_len + index_len).map_err(Error::Upload)?;
+
+        let mut map = transfer_buffer.map::<u8>(device, true);
+        let mem = map.mem_mut();
+        let mut offset = 0;
+        for bytes in std::iter::once(ui_vertex_bytes)
+            .chain(clip_vertices)
+            .chain([index_bytes])
+        {
+            mem[offset..offset + bytes.len()].copy_from_slice(bytes);
+            offset += bytes.len();
+        }
+        map.unmap();
+
+        let copy_pass = begin_upload(device, command_buffer, upload)?;
+        copy_pass.upload_to_gpu_buffer(
+            TransferBufferLocation::new()
+                .with_offset(0)
+                .with_transfer_buffer(transfer_buffer),
+            BufferRegion::new()
+                .with_offset(0)
+                .with_size(vertex_len)
+                .with_buffer(vertex_buffer),
+            true,
+        );
+        copy_pass.upload_to_gpu_buffer(
+            TransferBufferLocation::new()
+                .with_offset(vertex_len)
+                .with_transfer_buffer(transfer_buffer),
+            BufferRegion::new()
+                .with_offset(0)
+                .with_size(index_len)
+                .with_buffer(index_buffer),
+            true,
+        );
 
         Ok(())
     }
 
-    fn draw_command(
-        &self,
+    fn update_font_page(
+        &mut self,
         device: &Device,
-        pass: &RenderPass,
-        cmd: &mut CommandBuffer,
-        cmd_ui: &Command,
-        (fb_w, fb_h): (u32, u32),
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        // --- Scissor from clip bounds (simple clipping path)
-        let mut clip = cmd_ui.clip_bounds;
-        clip.position.x = clip.position.x.floor();
-        clip.position.y = clip.position.y.floor();
-        clip.size.x = clip.size.x.ceil();
-        clip.size.y = clip.size.y.ceil();
-
-        let sc_x = clip.position.x as i32;
-        let sc_y = clip.position.y as i32;
-        let sc_w = clip.size.x.max(0.0) as u32;
-        let sc_h = clip.size.y.max(0.0) as u32;
-
-        unsafe {
-            // SDL uses top-left origin for GPU scissor.
-            // If yours is bottom-left, flip Y accordingly. With SDL3 it’s top-left.
-            let rect = sdl3::rect::Rect::new(sc_x, sc_y, sc_w, sc_h);
-            sdl3::sys::gpu::SDL_SetGPUScissor(pass.raw(), rect.raw());
+        command_buffer: &CommandBuffer,
+        upload: &mut UploadPass,
+        font: &FontResource,
+        height: &FontHeight,
+        page_index: usize,
+    ) -> Result<(), Error> {
+        self.font_pages
+            .update_page(font, height, page_index, |pixels, page_size, cached| {
+                let copy_pass = begin_upload(device, command_buffer, upload)?;
+                match cached {
+                    Some(texture) => {
+                        update_texture(
+                            device,
+                            copy_pass,
+                            &mut self.upload_buffer,
+                            &texture,
+                            pixels,
+                            page_size,
+                            page_size,
+                            true,
+                        )
+                        .map_err(Error::Upload)?;
+                        Ok(texture)
+                    }
+                    None => create_texture(device, copy_pass, &mut self.upload_buffer, pixels, page_size, page_size)
+                        .map_err(Error::Upload),
+                }
+            })
+    }
+
+    fn update_texture(
+        &mut self,
+        device: &Device,
+        command_buffer: &CommandBuffer,
+        upload: &mut UploadPass,
+        resource: &TextureResource,
+    ) -> Result<(), Error> {
+        if self.external_textures.contains(resource) {
+            return Ok(());
         }
 
-        // --- Pack fyrox_widgetData
-        //
-        // This mirrors what Fyrox writes:
-        //  - worldViewProjection (we put this in vertex set=0 already)
-        //  - brush data: solid/gradient colors+stops, gradient points, resolution, bounds, flags, opacity
-        // To keep it short, we do the minimal subset most UI uses: solid brush + is_font flag + opacity.
-        // If you need gradients, extend this to match your fragment shader’s UBO layout.
-
-        #[repr(C)]
-        struct WidgetData {
-            // vec4 solid_color
-            solid: [f32; 4],
-            // vec2 resolution
-            resolution: [f32; 2],
-            // vec2 bounds_min
-            bounds_min: [f32; 2],
-            // vec2 bounds_max
-            bounds_max: [f32; 2],
-            // float is_font_texture (0.0/1.0)
-            is_font: f32,
-            // float opacity
-            opacity: f32,
-            // padding to 16B multiples
-            _pad: [f32; 2],
-        }
-
-        let solid = match cmd_ui.brush {
-            fyrox_ui::brush::Brush::Solid(c) => [
-                c.r as f32 / 255.0,
-                c.g as f32 / 255.0,
-                c.b as f32 / 255.0,
-                c.a as f32 / 255.0,
-            ],
-            _ => [1.0, 1.0, 1.0, 1.0], // extend for gradients if needed
-        };
+        self.textures.update(resource, |data, width, height, cached| {
+            let Some(format) = texture_format(data.pixel_kind()) else {
+                return Ok(None);
+            };
+            let mip_count = data.mip_count().max(1);
+            // Cycle the GPU texture if it may still be in use by a previous frame.
+            let cycle = cached.is_some();
 
-        let bounds_min = [cmd_ui.bounds.position.x, cmd_ui.bounds.position.y];
-        let br = cmd_ui.bounds.right_bottom_corner();
-        let bounds_max = [br.x, br.y];
+            // Reuse the GPU texture unless the layout of the data changed.
+            let texture = match cached {
+                Some(entry)
+                    if entry.width == width
+                        && entry.height == height
+                        && entry.pixel_kind == data.pixel_kind()
+                        && entry.mip_count == mip_count =>
+                {
+                    entry.texture
+                }
+                _ => create_texture_with_format(device, format, width, height, mip_count).map_err(Error::Upload)?,
+            };
 
-        let is_font = matches!(cmd_ui.texture, CommandTexture::Font { .. }) as i32 as f32;
+            let copy_pass = begin_upload(device, command_buffer, upload)?;
+            for level in 0..mip_count {
+                let (level_width, level_height) = ((width >> level).max(1), (height >> level).max(1));
+                let pixels = convert_texture_pixels(data.pixel_kind(), data.mip_level_data(level as usize));
+                update_texture_level(
+                    device,
+                    copy_pass,
+                    &mut self.upload_buffer,
+                    &texture,
+                    level,
+                    &pixels,
+                    level_width,
+                    level_height,
+                    cycle,
+                )
+                .map_err(Error::Upload)?;
+            }
 
-        let widget = WidgetData {
-            solid,
-            resolution: [fb_w as f32, fb_h as f32],
-            bounds_min,
-            bounds_max,
-            is_font,
-            opacity: cmd_ui.opacity,
-            _pad: [0.0, 0.0],
-        };
+            Ok(Some(TextureCacheEntry {
+                texture,
+                width,
+                height,
+                pixel_kind: data.pixel_kind(),
+                mip_count,
+                linear: is_linear_format(format),
+            }))
+        })
+    }
 
-        cmd.push_fragment_uniform_data(0, &widget);
+    fn draw_batch(
+        &self,
+        pass: &RenderPass,
+        cmd: &mut CommandBuffer,
+        state: &mut DrawState,
+        cmd_ui: &Command,
+        triangles: Range<usize>,
+        scale: &WindowScale,
+    ) {
+        // --- Scissor from clip bounds (simple clipping path), in framebuffer pixels
+        let scissor = scissor_rect(&cmd_ui.clip_bounds, scale);
+        if state.scissor != Some(scissor) {
+            state.scissor = Some(scissor);
+            set_scissor(pass, scissor);
+        }
 
-        // --- Bind texture
-        let (tex, samp) = match &cmd_ui.texture {
+        // --- Pick texture
+        let (tex, samp, linear_texture) = match &cmd_ui.texture {
             CommandTexture::Font {
                 font,
                 page_index,
                 height,
             } => {
-                let mut texture = None;
-
-                if let Some(font) = font.state().data() {
-                    let page_size = font.page_size() as u32;
-                    if let Some(page) = font
-                        .atlases
-                        .get_mut(height)
-                        .and_then(|atlas| atlas.pages.get_mut(*page_index))
-                    {
-                        // if page.texture.is_none() || page.modified {
-                        texture = {
-                            let copy_cmds = device.acquire_command_buffer().unwrap();
-                            let copy_pass = device.begin_copy_pass(&copy_cmds).unwrap();
-                            let tex = create_texture(device, &copy_pass, &page.pixels, page_size, page_size).unwrap();
-                            device.end_copy_pass(copy_pass);
-                            copy_cmds.submit().unwrap();
-                            Some(tex)
-                        };
-                        // }
-                    }
-                }
-
-                (&texture.unwrap_or(self.white_tex.clone()), &self.sampler_linear)
-
-                // Map page index to an uploaded SDL texture.
-                // let tex = self
-                //     .font_pages
-                //     .get(*page_index)
-                //     .unwrap_or(&self.white_tex);
-                // (tex, &self.sampler_linear)
+                let tex = self
+                    .font_pages
+                    .page(font, height, *page_index)
+                    .unwrap_or(&self.white_tex);
+                (tex, &self.sampler_linear, false)
             }
-            CommandTexture::Texture(_tex_res) => {
-                // If you have your own texture cache for UI, plug it here.
-                // For now, use white fallback.
-                (&self.white_tex, &self.sampler_linear)
+            CommandTexture::Texture(resource) => {
+                let (tex, linear) = self
+                    .external_textures
+                    .get(resource)
+                    .map(|entry| (&entry.texture, entry.linear))
+                    .or_else(|| {
+                        self.textures
+                            .texture(resource)
+                            .map(|entry| (&entry.texture, entry.linear))
+                    })
+                    .unwrap_or((&self.white_tex, false));
+                (tex, &self.sampler_linear, linear)
             }
-            _ => (&self.white_tex, &self.sampler_linear),
+            CommandTexture::None => (&self.white_tex, &self.sampler_linear, false),
         };
 
-        let binding = TextureSamplerBinding::new().with_texture(tex).with_sampler(samp);
-        pass.bind_fragment_samplers(0, &[binding]);
+        // --- Pack fyrox_widgetData
+        let widget = WidgetData::new(cmd_ui, scale, &self.fragment_output(), linear_texture);
+
+        if state.widget.as_ref() != Some(&widget) {
+            cmd.push_fragment_uniform_data(0, &widget);
+            state.widget = Some(widget);
+        }
+
+        // --- Bind texture
+        if state.texture != Some(tex.raw()) {
+            state.texture = Some(tex.raw());
+            let binding = TextureSamplerBinding::new().with_texture(tex).with_sampler(samp);
+            pass.bind_fragment_samplers(0, &[binding]);
+        }
 
         // --- Draw triangles range
-        let first_index = cmd_ui.triangles.start as u32 * 3;
-        let index_count = (cmd_ui.triangles.end - cmd_ui.triangles.start) as u32 * 3;
+        let first_index = triangles.start as u32 * 3;
+        let index_count = (triangles.end - triangles.start) as u32 * 3;
 
         pass.draw_indexed_primitives(index_count, 1, first_index, 0, 0);
+    }
+}
 
-        Ok(())
+/// Targets a UI pipeline renders to.
+struct PipelineTargets {
+    format: TextureFormat,
+    depth_stencil_format: Option<TextureFormat>,
+    sample_count: SampleCount,
+}
+
+/// Highest sample count up to `requested` supported by both target formats.
+fn supported_sample_count(
+    device: &Device,
+    format: TextureFormat,
+    depth_stencil_format: Option<TextureFormat>,
+    requested: SampleCount,
+) -> SampleCount {
+    let supports = |format: TextureFormat, sample_count: SampleCount| unsafe {
+        SDL_GPUTextureSupportsSampleCount(
+            device.raw(),
+            SDL_GPUTextureFormat(format as i32),
+            SDL_GPUSampleCount(sample_count as i32),
+        )
+    };
+
+    [SampleCount::MSAA8x, SampleCount::MSAA4x, SampleCount::MSAA2x]
+        .into_iter()
+        .filter(|&sample_count| sample_count <= requested)
+        .find(|&sample_count| {
+            supports(format, sample_count)
+                && depth_stencil_format.is_none_or(|depth_stencil_format| supports(depth_stencil_format, sample_count))
+        })
+        .unwrap_or(SampleCount::NoMultiSampling)
+}
+
+/// Build the pipelines of every blend mode for `targets`.
+///
+/// Returns the pipeline and stencil pipelines of `blend_mode`, followed by the pipelines of all
+/// modes in the order of `BlendMode::ALL`.
+fn create_pipelines(
+    device: &Rc<Device>,
+    shaders: &Shaders,
+    targets: &PipelineTargets,
+    blend_mode: BlendMode,
+) -> Result<(Pipeline, Option<StencilPipelines>, Vec<BlendPipelines>), Error> {
+    // Switching modes only swaps pipelines, so all of them are built up front.
+    let blend_pipelines = BlendMode::ALL
+        .into_iter()
+        .map(|mode| {
+            Ok(BlendPipelines {
+                pipeline: create_pipeline(device, shaders, targets, StencilMode::Ignore, mode)?,
+                stencil_test: match targets.depth_stencil_format {
+                    Some(_) => Some(create_pipeline(device, shaders, targets, StencilMode::Test, mode)?),
+                    None => None,
+                },
+                composite: match targets.sample_count {
+                    SampleCount::NoMultiSampling => None,
+                    _ => Some(create_composite_pipeline(device, shaders, targets.format, mode)?),
+                },
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let current = &blend_pipelines[blend_mode as usize];
+    let pipeline = current.pipeline.clone();
+    let stencil = match &current.stencil_test {
+        Some(test) => Some(StencilPipelines {
+            // Color writes are disabled, so the blend mode doesn't matter.
+            write: create_pipeline(device, shaders, targets, StencilMode::Write, BlendMode::Alpha)?,
+            test: test.clone(),
+        }),
+        None => None,
+    };
+
+    Ok((pipeline, stencil, blend_pipelines))
+}
+
+/// Build the UI pipeline for the given targets, stencil usage and blend mode.
+fn create_pipeline(
+    device: &Rc<Device>,
+    shaders: &Shaders,
+    targets: &PipelineTargets,
+    stencil: StencilMode,
+    blend_mode: BlendMode,
+) -> Result<Pipeline, Error> {
+    let [(src_color, dst_color), (src_alpha, dst_alpha)] = blend_mode.blend_factors();
+    let mut blend_state = SDL_GPUColorTargetBlendState {
+        src_color_blendfactor: SDL_GPUBlendFactor(src_color as i32),
+        dst_color_blendfactor: SDL_GPUBlendFactor(dst_color as i32),
+        color_blend_op: SDL_GPUBlendOp::ADD,
+        src_alpha_blendfactor: SDL_GPUBlendFactor(src_alpha as i32),
+        dst_alpha_blendfactor: SDL_GPUBlendFactor(dst_alpha as i32),
+        alpha_blend_op: SDL_GPUBlendOp::ADD,
+        enable_blend: true,
+        ..Default::default()
+    };
+    if stencil == StencilMode::Write {
+        // Clip geometry only marks the stencil, it must not show up in the color target.
+        blend_state.color_write_mask = 0;
+        blend_state.enable_color_write_mask = true;
+    }
+
+    let color_targets = [SDL_GPUColorTargetDescription {
+        format: SDL_GPUTextureFormat(targets.format as i32),
+        blend_state,
+    }];
+    let mut target_info = SDL_GPUGraphicsPipelineTargetInfo {
+        color_target_descriptions: color_targets.as_ptr(),
+        num_color_targets: color_targets.len() as u32,
+        ..Default::default()
+    };
+    if let Some(depth_stencil_format) = targets.depth_stencil_format {
+        target_info.depth_stencil_format = SDL_GPUTextureFormat(depth_stencil_format as i32);
+        target_info.has_depth_stencil_target = true;
+    }
+
+    let stencil_op = match stencil {
+        StencilMode::Ignore => None,
+        StencilMode::Write => Some((SDL_GPUCompareOp::ALWAYS, SDL_GPUStencilOp::REPLACE, 0xFF)),
+        StencilMode::Test => Some((SDL_GPUCompareOp::EQUAL, SDL_GPUStencilOp::KEEP, 0x00)),
+    };
+    let mut depth_stencil_state = SDL_GPUDepthStencilState::default();
+    if let Some((compare_op, pass_op, write_mask)) = stencil_op {
+        // UI geometry winding isn't consistent, so both faces use the same state.
+        let op_state = SDL_GPUStencilOpState {
+            fail_op: SDL_GPUStencilOp::KEEP,
+            pass_op,
+            depth_fail_op: SDL_GPUStencilOp::KEEP,
+            compare_op,
+        };
+        depth_stencil_state = SDL_GPUDepthStencilState {
+            front_stencil_state: op_state,
+            back_stencil_state: op_state,
+            compare_mask: 0xFF,
+            write_mask,
+            enable_stencil_test: true,
+            ..Default::default()
+        };
+    }
+
+    let vertex_buffers = [SDL_GPUVertexBufferDescription {
+        slot: 0,
+        pitch: std::mem::size_of::<fyrox_ui::draw::Vertex>() as u32,
+        input_rate: SDL_GPUVertexInputRate::VERTEX,
+        instance_step_rate: 0,
+    }];
+    let attribute = |location, format, offset: usize| SDL_GPUVertexAttribute {
+        location,
+        buffer_slot: 0,
+        format,
+        offset: offset as u32,
+    };
+    let vertex_attributes = [
+        attribute(
+            0,
+            SDL_GPUVertexElementFormat::FLOAT2,
+            offset_of!(fyrox_ui::draw::Vertex, pos),
+        ),
+        attribute(
+            1,
+            SDL_GPUVertexElementFormat::FLOAT2,
+            offset_of!(fyrox_ui::draw::Vertex, tex_coord),
+        ),
+        attribute(
+            2,
+            SDL_GPUVertexElementFormat::UBYTE4_NORM,
+            offset_of!(fyrox_ui::draw::Vertex, color),
+        ),
+    ];
+
+    let info = SDL_GPUGraphicsPipelineCreateInfo {
+        vertex_shader: shaders.vert.raw(),
+        fragment_shader: shaders.frag.raw(),
+        vertex_input_state: SDL_GPUVertexInputState {
+            vertex_buffer_descriptions: vertex_buffers.as_ptr(),
+            num_vertex_buffers: vertex_buffers.len() as u32,
+            vertex_attributes: vertex_attributes.as_ptr(),
+            num_vertex_attributes: vertex_attributes.len() as u32,
+        },
+        primitive_type: SDL_GPUPrimitiveType::TRIANGLELIST,
+        // Culling stays disabled for UI geometry.
+        rasterizer_state: SDL_GPURasterizerState {
+            fill_mode: SDL_GPUFillMode::FILL,
+            cull_mode: SDL_GPUCullMode::NONE,
+            front_face: SDL_GPUFrontFace::CLOCKWISE,
+            ..Default::default()
+        },
+        multisample_state: SDL_GPUMultisampleState {
+            sample_count: SDL_GPUSampleCount(targets.sample_count as i32),
+            ..Default::default()
+        },
+        depth_stencil_state,
+        target_info,
+        ..Default::default()
+    };
+
+    Pipeline::new(device, &info)
+}
+
+/// Build the pipeline compositing the resolved UI layer over single sample targets of `format`,
+/// see [`BlendMode::layer_blend_factors`].
+fn create_composite_pipeline(
+    device: &Device,
+    shaders: &Shaders,
+    format: TextureFormat,
+    blend_mode: BlendMode,
+) -> Result<GraphicsPipeline, Error> {
+    let [(src_color, dst_color), (src_alpha, dst_alpha)] = blend_mode.layer_blend_factors();
+    let blend_state = ColorTargetBlendState::new()
+        .with_color_blend_op(BlendOp::Add)
+        .with_src_color_blendfactor(src_color)
+        .with_dst_color_blendfactor(dst_color)
+        .with_alpha_blend_op(BlendOp::Add)
+        .with_src_alpha_blendfactor(src_alpha)
+        .with_dst_alpha_blendfactor(dst_alpha)
+        .with_enable_blend(true);
+    let color_targets = [ColorTargetDescription::new()
+        .with_format(format)
+        .with_blend_state(blend_state)];
+
+    device
+        .create_graphics_pipeline()
+        .with_vertex_shader(&shaders.composite_vert)
+        .with_fragment_shader(&shaders.composite_frag)
+        .with_primitive_type(PrimitiveType::TriangleList)
+        .with_rasterizer_state(RasterizerState::new().with_fill_mode(FillMode::Fill))
+        .with_target_info(GraphicsPipelineTargetInfo::new().with_color_target_descriptions(&color_targets))
+        .build()
+        .map_err(Error::Pipeline)
+}
+
+/// Resolve `samples` into `resolved` with an empty render pass.
+///
+/// `ColorTargetInfo` has no setter for the resolve texture, so the pass is begun through SDL
+/// directly. The pass records no draws, hence no `RenderPass` is needed.
+fn resolve(command_buffer: &CommandBuffer, samples: &Texture, resolved: &Texture) -> Result<(), Error> {
+    let info = SDL_GPUColorTargetInfo {
+        texture: samples.raw(),
+        load_op: LoadOp::LOAD,
+        store_op: StoreOp::RESOLVE,
+        resolve_texture: resolved.raw(),
+        // The whole texture is overwritten, so SDL can hand out a free one if it is still in use.
+        cycle_resolve_texture: true,
+        ..Default::default()
+    };
+    unsafe {
+        let pass = SDL_BeginGPURenderPass(command_buffer.raw(), &info, 1, std::ptr::null());
+        if pass.is_null() {
+            return Err(Error::Command(sdl3::get_error()));
+        }
+        SDL_EndGPURenderPass(pass);
+    }
+
+    Ok(())
+}
+
+/// Returns `true` if `drawing` has no geometry to upload nor draw.
+fn is_empty(drawing: &DrawingContext) -> bool {
+    drawing.get_vertices().is_empty() || drawing.get_triangles().is_empty()
+}
+
+/// Scissor rectangle `(x, y, width, height)` covering `clip_bounds`, in framebuffer pixels.
+fn scissor_rect(clip_bounds: &Rect<f32>, scale: &WindowScale) -> (i32, i32, u32, u32) {
+    let mut clip = *clip_bounds;
+    clip.position *= scale.display_scale;
+    clip.size *= scale.display_scale;
+    clip.position.x = clip.position.x.floor();
+    clip.position.y = clip.position.y.floor();
+    clip.size.x = clip.size.x.ceil();
+    clip.size.y = clip.size.y.ceil();
+
+    (
+        clip.position.x as i32,
+        clip.position.y as i32,
+        clip.size.x.max(0.0) as u32,
+        clip.size.y.max(0.0) as u32,
+    )
+}
+
+fn set_scissor(pass: &RenderPass, (x, y, width, height): (i32, i32, u32, u32)) {
+    unsafe {
+        // SDL uses top-left origin for GPU scissor.
+        // If yours is bottom-left, flip Y accordingly. With SDL3 it’s top-left.
+        let rect = sdl3::rect::Rect::new(x, y, width, height);
+        sdl3::sys::gpu::SDL_SetGPUScissor(pass.raw(), rect.raw());
+    }
+}
+
+/// Set the value stencil tests compare against and `Replace` writes.
+fn set_stencil_reference(pass: &RenderPass, reference: u8) {
+    unsafe {
+        sdl3::sys::gpu::SDL_SetGPUStencilReference(pass.raw(), reference);
+    }
+}
+
+/// GPU state set by the previous draw of a render pass, used to skip redundant state changes.
+#[derive(Default)]
+struct DrawState {
+    scissor: Option<(i32, i32, u32, u32)>,
+    widget: Option<WidgetData>,
+    texture: Option<*mut SDL_GPUTexture>,
+}
+
+/// Split `commands` into runs that can be drawn with a single draw call.
+///
+/// Yields the first command of each run, whose state is used for the whole run, along with the
+/// range of triangles covered by the run.
+pub(crate) fn batches(commands: &[Command]) -> impl Iterator<Item = (&Command, Range<usize>)> {
+    let mut i = 0;
+    std::iter::from_fn(move || {
+        let first = commands.get(i)?;
+        let mut triangles = first.triangles.clone();
+        i += 1;
+        while let Some(next) = commands.get(i) {
+            if !can_merge(first, next, triangles.end) {
+                break;
+            }
+            triangles.end = next.triangles.end;
+            i += 1;
+        }
+        Some((first, triangles))
+    })
+}
+
+/// Returns `true` if `next` can be drawn in the same draw call as the run started by `first` and
+/// ending at triangle `end`.
+fn can_merge(first: &Command, next: &Command, end: usize) -> bool {
+    // Gradients are evaluated relative to the command bounds, solid brushes ignore them.
+    let same_bounds = matches!(first.brush, Brush::Solid(_)) || first.bounds == next.bounds;
+
+    next.triangles.start == end
+        && first.clipping_geometry.is_none()
+        && next.clipping_geometry.is_none()
+        && first.opacity == next.opacity
+        && first.clip_bounds == next.clip_bounds
+        && first.brush == next.brush
+        && same_bounds
+        && same_texture(&first.texture, &next.texture)
+}
+
+fn same_texture(a: &CommandTexture, b: &CommandTexture) -> bool {
+    match (a, b) {
+        (CommandTexture::None, CommandTexture::None) => true,
+        (CommandTexture::Texture(a), CommandTexture::Texture(b)) => a.key() == b.key(),
+        (
+            CommandTexture::Font {
+                font: font_a,
+                height: height_a,
+                page_index: page_a,
+            },
+            CommandTexture::Font {
+                font: font_b,
+                height: height_b,
+                page_index: page_b,
+            },
+        ) => font_a.key() == font_b.key() && height_a == height_b && page_a == page_b,
+        _ => false,
+    }
+}
+
+/// Return the copy pass for uploads, beginning it on `command_buffer` on first use.
+fn begin_upload<'a>(
+    device: &Device,
+    command_buffer: &CommandBuffer,
+    upload: &'a mut UploadPass,
+) -> Result<&'a CopyPass, Error> {
+    match upload {
+        Some(copy_pass) => Ok(copy_pass),
+        None => Ok(upload.insert(device.begin_copy_pass(command_buffer).map_err(Error::Command)?)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::readback::submit_with_readback;
+    use crate::test_support::TestGpu;
+    use fyrox_ui::core::color::Color;
+    use fyrox_ui::draw::Draw;
+    use fyrox_ui::style::DEFAULT_STYLE;
+
+    /// 4×4 color target of `format`, downloadable with [`submit_with_readback`].
+    fn create_target(device: &Device, format: TextureFormat) -> ReadbackTarget {
+        ReadbackTarget::new(device, format, 4, 4).unwrap()
+    }
+
+    /// Drawing context filling a 4×4 target with `color`.
+    fn fill(color: Color) -> DrawingContext {
+        let mut drawing = DrawingContext::new(DEFAULT_STYLE.resource.clone());
+        drawing.push_rect_filled(&Rect::new(0.0, 0.0, 4.0, 4.0), None);
+        drawing.commit(
+            Rect::new(0.0, 0.0, 4.0, 4.0),
+            Brush::Solid(color),
+            CommandTexture::None,
+            None,
+        );
+        drawing
+    }
+
+    /// Fill a 4×4 target of `format` with `color` through `renderer` and download it.
+    fn render_fill(device: &Device, renderer: &mut UiRenderer, format: TextureFormat, color: Color) -> Vec<u8> {
+        let target = create_target(device, format);
+        let color_targets = [ColorTargetInfo::default()
+            .with_texture(target.texture())
+            .with_load_op(LoadOp::CLEAR)
+            .with_store_op(StoreOp::STORE)];
+
+        let mut command_buffer = device.acquire_command_buffer().unwrap();
+        renderer
+            .render_to_target(device, &mut command_buffer, &color_targets, None, (4, 4), &fill(color))
+            .unwrap();
+        submit_with_readback(device, command_buffer, &target.source())
+            .unwrap()
+            .wait()
+            .unwrap()
+            .pixels
+    }
+
+    #[test]
+    fn fragment_output_follows_the_target() {
+        let hdr_scale = DEFAULT_HDR_BRIGHTNESS / SCRGB_WHITE_NITS;
+        let cases = [
+            (TextureFormat::B8g8r8a8Unorm, SwapchainComposition::Sdr, false, 1.0),
+            (TextureFormat::R8g8b8a8Unorm, SwapchainComposition::Sdr, false, 1.0),
+            (
+                TextureFormat::B8g8r8a8UnormSrgb,
+                SwapchainComposition::SdrLinear,
+                true,
+                1.0,
+            ),
+            // Offscreen targets keep the default composition.
+            (TextureFormat::R8g8b8a8UnormSrgb, SwapchainComposition::Sdr, true, 1.0),
+            (TextureFormat::R16g16b16a16Float, SwapchainComposition::Sdr, true, 1.0),
+            (
+                TextureFormat::R16g16b16a16Float,
+                SwapchainComposition::HdrExtendedLinear,
+                true,
+                hdr_scale,
+            ),
+            // The swapchain format may not have switched yet.
+            (
+                TextureFormat::B8g8r8a8Unorm,
+                SwapchainComposition::HdrExtendedLinear,
+                false,
+                1.0,
+            ),
+        ];
+
+        for (format, composition, linear, scale) in cases {
+            let output = FragmentOutput::new(format, composition, DEFAULT_HDR_BRIGHTNESS, BlendMode::Alpha);
+            let expected = FragmentOutput {
+                premultiply: false,
+                linear,
+                scale,
+            };
+            assert_eq!(output, expected, "{format:?} on a {composition:?} swapchain");
+        }
+    }
+
+    #[test]
+    fn fragment_output_scales_to_the_hdr_brightness() {
+        let output = FragmentOutput::new(
+            TextureFormat::R16g16b16a16Float,
+            SwapchainComposition::HdrExtendedLinear,
+            400.0,
+            BlendMode::Alpha,
+        );
+        assert_eq!(output.scale, 5.0);
+    }
+
+    #[test]
+    fn fragment_output_premultiplies_but_for_straight_alpha() {
+        for mode in BlendMode::ALL {
+            let output = FragmentOutput::new(
+                TextureFormat::R8g8b8a8Unorm,
+                SwapchainComposition::Sdr,
+                DEFAULT_HDR_BRIGHTNESS,
+                mode,
+            );
+            assert_eq!(output.premultiply, mode != BlendMode::Alpha, "{mode:?}");
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a GPU device, run with --ignored"]
+    fn set_format_rebuilds_the_pipelines() {
+        let gpu = TestGpu::new();
+        let device = &gpu.device;
+
+        let config = UiRendererConfig {
+            sample_count: SampleCount::MSAA4x,
+            ..Default::default()
+        };
+        let mut renderer = UiRenderer::with_format(device, TextureFormat::R8g8b8a8Unorm, config).unwrap();
+        renderer.set_blend_mode(BlendMode::Premultiplied);
+        renderer.set_format(device, TextureFormat::R8g8b8a8UnormSrgb).unwrap();
+
+        assert_eq!(renderer.format(), TextureFormat::R8g8b8a8UnormSrgb);
+        assert_eq!(renderer.blend_mode(), BlendMode::Premultiplied);
+        assert_eq!(renderer.blend_pipelines.len(), BlendMode::ALL.len());
+        assert!(renderer.fragment_output().linear);
+
+        // Fyrox colors are sRGB encoded, so they come out unchanged of an sRGB target.
+        let pixels = render_fill(
+            device,
+            &mut renderer,
+            TextureFormat::R8g8b8a8UnormSrgb,
+            Color::opaque(255, 128, 0),
+        );
+        for pixel in pixels.chunks_exact(4) {
+            let close = pixel.iter().zip([255, 128, 0, 255]).all(|(&a, e)| a.abs_diff(e) <= 1);
+            assert!(close, "expected orange, got {pixel:?}");
+        }
+    }
+    #[test]
+    #[ignore = "needs a GPU device, run with --ignored"]
+    fn readback_returns_rgba_of_bgra_targets() {
+        let gpu = TestGpu::new();
+        let device = &gpu.device;
+
+        let mut renderer =
+            UiRenderer::with_format(device, TextureFormat::B8g8r8a8Unorm, UiRendererConfig::default()).unwrap();
+        let pixels = render_fill(
+            device,
+            &mut renderer,
+            TextureFormat::B8g8r8a8Unorm,
+            Color::opaque(255, 128, 0),
+        );
+        assert_eq!(pixels.len(), 4 * 4 * 4);
+        for pixel in pixels.chunks_exact(4) {
+            let close = pixel.iter().zip([255, 128, 0, 255]).all(|(&a, e)| a.abs_diff(e) <= 1);
+            assert!(close, "expected orange, got {pixel:?}");
+        }
+    }
+    #[test]
+    #[ignore = "needs a GPU device, run with --ignored"]
+    fn prepared_frame_draws_into_the_callers_pass() {
+        let gpu = TestGpu::new();
+        let device = &gpu.device;
+        let mut renderer =
+            UiRenderer::with_format(device, TextureFormat::R8g8b8a8Unorm, UiRendererConfig::default()).unwrap();
+        let target = create_target(device, TextureFormat::R8g8b8a8Unorm);
+        let color_targets = [ColorTargetInfo::default()
+            .with_texture(target.texture())
+            .with_load_op(LoadOp::CLEAR)
+            .with_store_op(StoreOp::STORE)];
+        let scale = WindowScale {
+            size_in_pixels: (4, 4),
+            ..Default::default()
+        };
+        let drawing = fill(Color::opaque(255, 128, 0));
+
+        let mut command_buffer = device.acquire_command_buffer().unwrap();
+        let prepared = renderer.prepare(device, &command_buffer, &drawing).unwrap();
+        let render_pass = device.begin_render_pass(&command_buffer, &color_targets, None).unwrap();
+        prepared
+            .draw(device, &mut command_buffer, &render_pass, &scale)
+            .unwrap();
+        device.end_render_pass(render_pass);
+        assert_eq!(renderer.stats().draw_calls, 1);
+
+        let pixels = submit_with_readback(device, command_buffer, &target.source())
+            .unwrap()
+            .wait()
+            .unwrap()
+            .pixels;
+        for pixel in pixels.chunks_exact(4) {
+            assert_eq!(pixel, [255, 128, 0, 255]);
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a GPU device, run with --ignored"]
+    fn renderer_releases_its_pipelines_after_the_device_handle() {
+        let _gpu = TestGpu::new();
+        let device = Device::new(ShaderFormat::SPIRV, false).unwrap();
+        let config = UiRendererConfig {
+            sample_count: SampleCount::MSAA4x,
+            ..Default::default()
+        };
+        let renderer = UiRenderer::with_format(&device, TextureFormat::R8g8b8a8Unorm, config).unwrap();
+
+        // The renderer keeps the device alive until its pipelines are released.
+        drop(device);
+        drop(renderer);
     }
 }
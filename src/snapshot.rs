@@ -0,0 +1,346 @@
+//! Golden-image testing of user interfaces.
+//!
+//! A [`Snapshot`] updates a [`FyroxUiSdl`] for a few frames, renders it offscreen through its GPU
+//! [`UiRenderer`](crate::renderer::UiRenderer), downloads the pixels and compares them against a
+//! reference PNG. [`Headless`] opens a GPU device without any window for it, so the tests run on
+//! CI machines with a software Vulkan driver such as lavapipe.
+//!
+//! ```no_run
+//! # use fyrox_ui_sdl3::{FyroxUiSdl, renderer::UiRendererConfig};
+//! # use fyrox_ui_sdl3::snapshot::{Headless, Snapshot};
+//! # use sdl3::gpu::TextureFormat;
+//! let headless = Headless::new().unwrap();
+//! let mut fyrox_ui = FyroxUiSdl::with_format(
+//!     &headless.device,
+//!     TextureFormat::R8g8b8a8Unorm,
+//!     (320, 240),
+//!     UiRendererConfig::default(),
+//! )
+//! .unwrap();
+//! // build the widgets in `fyrox_ui.ui`...
+//! Snapshot::new(320, 240)
+//!     .with_tolerance(2)
+//!     .compare(&headless.device, &mut fyrox_ui, "tests/snapshots/settings.png")
+//!     .unwrap();
+//! ```
+//!
+//! A missing reference fails the comparison, with the render written next to it. Set the
+//! `UPDATE_SNAPSHOTS` environment variable to record the missing references, or to overwrite
+//! them after an intended change of look.
+
+use crate::FyroxUiSdl;
+use crate::error::Error;
+use crate::platform::WindowScale;
+use crate::readback::submit_with_readback;
+use fyrox_ui::core::color::Color;
+use sdl3::gpu::{
+    ColorTargetInfo, CommandBuffer, DepthStencilTargetInfo, Device, LoadOp, ShaderFormat, StoreOp, Texture,
+    TextureCreateInfo, TextureType, TextureUsage,
+};
+use sdl3::{Sdl, VideoSubsystem};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+/// Environment variable that makes [`Snapshot::compare`] record or overwrite the reference images.
+pub const UPDATE_ENV: &str = "UPDATE_SNAPSHOTS";
+
+/// GPU device without any window, to render snapshots on.
+pub struct Headless {
+    pub device: Device,
+    _video: VideoSubsystem,
+    _sdl: Sdl,
+}
+
+impl Headless {
+    /// Initialize SDL with the `offscreen` video driver, unless `SDL_VIDEO_DRIVER` is set, and
+    /// create a SPIR-V GPU device.
+    pub fn new() -> Result<Self, Error> {
+        if std::env::var_os("SDL_VIDEO_DRIVER").is_none() {
+            sdl3::hint::set("SDL_VIDEO_DRIVER", "offscreen");
+        }
+        let sdl = sdl3::init().map_err(Error::Headless)?;
+        let video = sdl.video().map_err(Error::Headless)?;
+        let device = Device::new(ShaderFormat::SPIRV, false).map_err(Error::Headless)?;
+        Ok(Self {
+            device,
+            _video: video,
+            _sdl: sdl,
+        })
+    }
+}
+
+/// Options of a golden-image comparison.
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    width: u32,
+    height: u32,
+    frames: usize,
+    dt: f32,
+    tolerance: u8,
+    background: Color,
+}
+
+impl Snapshot {
+    /// Compare `width`×`height` pixel renders after one frame of update, on a transparent
+    /// background and without tolerance.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            frames: 1,
+            dt: 1.0 / 60.0,
+            tolerance: 0,
+            background: Color::TRANSPARENT,
+        }
+    }
+
+    /// Update the UI `frames` times with a `dt` time step before rendering, e.g. to let
+    /// animations settle.
+    pub fn with_frames(mut self, frames: usize, dt: f32) -> Self {
+        self.frames = frames;
+        self.dt = dt;
+        self
+    }
+
+    /// Maximum difference of any channel for two pixels to be considered equal.
+    pub fn with_tolerance(mut self, tolerance: u8) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Color the target is cleared to before rendering.
+    pub fn with_background(mut self, background: Color) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// Resize and update `fyrox_ui`, render it into a texture of its renderer's format and
+    /// download the RGBA8 pixels.
+    ///
+    /// The pixels are those of the target, i.e. premultiplied unless the renderer uses
+    /// [`BlendMode::Alpha`](crate::renderer::BlendMode::Alpha), and sRGB encoded.
+    pub fn render(&self, device: &Device, fyrox_ui: &mut FyroxUiSdl) -> Result<Vec<u8>, Error> {
+        let scale = WindowScale {
+            size_in_pixels: (self.width, self.height),
+            ..fyrox_ui.platform.window_scale()
+        };
+        fyrox_ui.platform.set_window_scale(scale);
+        let size = scale.ui_size();
+        fyrox_ui.resize(size.x, size.y);
+        for _ in 0..self.frames {
+            fyrox_ui.update(self.dt);
+        }
+
+        let target = fyrox_ui
+            .renderer
+            .create_readback_target(device, self.width, self.height)?;
+        let mut depth_stencil = match fyrox_ui.renderer.config().depth_stencil_format {
+            Some(format) => Some(self.create_target(device, format, TextureUsage::DEPTH_STENCIL_TARGET)?),
+            None => None,
+        };
+        let depth_stencil_target = depth_stencil.as_mut().map(|texture| {
+            DepthStencilTargetInfo::new()
+                .with_texture(texture)
+                .with_load_op(LoadOp::CLEAR)
+                .with_store_op(StoreOp::DONT_CARE)
+                .with_stencil_load_op(LoadOp::CLEAR)
+                .with_stencil_store_op(StoreOp::DONT_CARE)
+        });
+
+        let mut command_buffer = device.acquire_command_buffer().map_err(Error::Command)?;
+        if let Err(err) = self.record(
+            device,
+            fyrox_ui,
+            &mut command_buffer,
+            target.texture(),
+            depth_stencil_target.as_ref(),
+        ) {
+            command_buffer.cancel();
+            return Err(err);
+        }
+
+        let screenshot = submit_with_readback(device, command_buffer, &target.source())?.wait()?;
+        Ok(screenshot.pixels)
+    }
+
+    /// Clear `target` to the background, then render the UI over it. The UI is rendered in its
+    /// own pass because nothing is drawn at all for an empty UI.
+    fn record(
+        &self,
+        device: &Device,
+        fyrox_ui: &mut FyroxUiSdl,
+        command_buffer: &mut CommandBuffer,
+        target: &Texture,
+        depth_stencil_target: Option<&DepthStencilTargetInfo>,
+    ) -> Result<(), Error> {
+        let [r, g, b, a] = [
+            self.background.r,
+            self.background.g,
+            self.background.b,
+            self.background.a,
+        ];
+        let clear = [ColorTargetInfo::default()
+            .with_texture(target)
+            .with_load_op(LoadOp::CLEAR)
+            .with_store_op(StoreOp::STORE)
+            .with_clear_color(sdl3::pixels::Color::RGBA(r, g, b, a))];
+        let render_pass = device
+            .begin_render_pass(command_buffer, &clear, None)
+            .map_err(Error::Command)?;
+        device.end_render_pass(render_pass);
+
+        let color_targets = [ColorTargetInfo::default()
+            .with_texture(target)
+            .with_load_op(LoadOp::LOAD)
+            .with_store_op(StoreOp::STORE)];
+        fyrox_ui.render_to_target(
+            device,
+            command_buffer,
+            &color_targets,
+            depth_stencil_target,
+            (self.width, self.height),
+        )
+    }
+
+    fn create_target(
+        &self,
+        device: &Device,
+        format: sdl3::gpu::TextureFormat,
+        usage: TextureUsage,
+    ) -> Result<Texture<'static>, Error> {
+        device
+            .create_texture(
+                TextureCreateInfo::new()
+                    .with_format(format)
+                    .with_type(TextureType::_2D)
+                    .with_width(self.width)
+                    .with_height(self.height)
+                    .with_layer_count_or_depth(1)
+                    .with_num_levels(1)
+                    .with_usage(usage),
+            )
+            .map_err(Error::Upload)
+    }
+
+    /// Render `fyrox_ui` and compare it against the PNG at `reference`.
+    ///
+    /// On failure the render is written next to the reference with an `.actual.png` extension,
+    /// along with a `.diff.png` image where mismatched pixels are red. A missing reference is a
+    /// failure too, unless [`UPDATE_ENV`] is set.
+    pub fn compare(
+        &self,
+        device: &Device,
+        fyrox_ui: &mut FyroxUiSdl,
+        reference: impl AsRef<Path>,
+    ) -> Result<(), Error> {
+        let reference = reference.as_ref();
+        let actual = self.render(device, fyrox_ui)?;
+
+        if std::env::var_os(UPDATE_ENV).is_some() {
+            return write_png(reference, self.width, self.height, &actual);
+        }
+        if !reference.exists() {
+            let actual_path = sibling(reference, "actual");
+            write_png(&actual_path, self.width, self.height, &actual)?;
+            return Err(Error::SnapshotMissing {
+                path: reference.to_path_buf(),
+                actual: actual_path,
+            });
+        }
+        let (width, height, expected) = read_png(reference)?;
+        if (width, height) != (self.width, self.height) {
+            write_png(&sibling(reference, "actual"), self.width, self.height, &actual)?;
+            return Err(Error::SnapshotSize {
+                path: reference.to_path_buf(),
+                expected: (width, height),
+                found: (self.width, self.height),
+            });
+        }
+
+        let mut mismatched = 0;
+        let diff: Vec<u8> = expected
+            .chunks_exact(4)
+            .zip(actual.chunks_exact(4))
+            .flat_map(|(expected, actual)| {
+                let equal = expected
+                    .iter()
+                    .zip(actual)
+                    .all(|(&e, &a)| e.abs_diff(a) <= self.tolerance);
+                if equal {
+                    // Dimmed grey reference, so the red pixels stand out in context.
+                    let luma = (expected[0] as u32 + expected[1] as u32 + expected[2] as u32) / 9;
+                    let luma = (luma * expected[3] as u32 / 255) as u8;
+                    [luma, luma, luma, 255]
+                } else {
+                    mismatched += 1;
+                    [255, 0, 0, 255]
+                }
+            })
+            .collect();
+
+        if mismatched == 0 {
+            return Ok(());
+        }
+
+        let diff_path = sibling(reference, "diff");
+        write_png(&sibling(reference, "actual"), self.width, self.height, &actual)?;
+        write_png(&diff_path, self.width, self.height, &diff)?;
+        Err(Error::SnapshotMismatch {
+            path: reference.to_path_buf(),
+            pixels: mismatched,
+            diff: diff_path,
+        })
+    }
+}
+
+/// `dir/name.png` becomes `dir/name.<suffix>.png`.
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{stem}.{suffix}.png"))
+}
+
+/// Wrap an I/O or PNG error with the path of the file being accessed.
+fn file_error<E>(path: &Path) -> impl FnOnce(E) -> Error + '_
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    move |err| Error::SnapshotFile {
+        path: path.to_path_buf(),
+        source: err.into(),
+    }
+}
+
+/// Decode a PNG to straight alpha RGBA8 pixels, whatever its color type.
+fn read_png(path: &Path) -> Result<(u32, u32, Vec<u8>), Error> {
+    let file = File::open(path).map_err(file_error(path))?;
+    let mut decoder = png::Decoder::new(BufReader::new(file));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(file_error(path))?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(file_error(path))?;
+    buffer.truncate(info.buffer_size());
+
+    let pixels = match info.color_type {
+        png::ColorType::Rgba => buffer,
+        png::ColorType::Rgb => buffer.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        png::ColorType::GrayscaleAlpha => buffer.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        // Indexed images are expanded to RGB(A) by the transformations.
+        png::ColorType::Grayscale | png::ColorType::Indexed => buffer.iter().flat_map(|&l| [l, l, l, 255]).collect(),
+    };
+
+    Ok((info.width, info.height, pixels))
+}
+
+fn write_png(path: &Path, width: u32, height: u32, pixels: &[u8]) -> Result<(), Error> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(file_error(path))?;
+    }
+    let file = File::create(path).map_err(file_error(path))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(file_error(path))?;
+    writer.write_image_data(pixels).map_err(file_error(path))?;
+    writer.finish().map_err(file_error(path))
+}
@@ -0,0 +1,599 @@
+//! Pure CPU renderer backend that rasterizes Fyrox-UI `DrawingContext` into an RGBA buffer.
+//!
+//! It needs neither SDL video nor a GPU, which makes it suitable for headless thumbnails and
+//! deterministic tests. Fragments are shaded like `ui.frag` and blended like the
+//! [`UiRenderer`](crate::renderer::UiRenderer) pipeline, clipping geometry is honoured through a
+//! coverage mask. Textures are sampled bilinearly from their first mip level.
+
+use crate::cache::{ResourceCache, TextureCache};
+use crate::renderer::{RenderStats, batches};
+use crate::utils::{brush_color, color_to_array, rgba_pixels};
+use fyrox_ui::core::algebra::Vector2;
+use fyrox_ui::core::color::Color;
+use fyrox_ui::core::math::Rect;
+use fyrox_ui::core::math::TriangleDefinition;
+use fyrox_ui::draw::{Command, CommandTexture, DrawingContext, Vertex};
+use std::convert::Infallible;
+use std::ops::Range;
+
+/// RGBA copy of a user texture resource.
+struct TextureCacheEntry {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+/// Texels of a texture, tightly packed rows of `channels` bytes per texel.
+struct Image<'a> {
+    width: usize,
+    height: usize,
+    channels: usize,
+    pixels: &'a [u8],
+}
+
+impl Image<'_> {
+    /// Bilinear sample with clamp-to-edge addressing, like the GPU sampler.
+    ///
+    /// Single channel images are font atlases, sampled as white with coverage as alpha.
+    fn sample(&self, uv: Vector2<f32>) -> [f32; 4] {
+        if self.width == 0 || self.height == 0 {
+            return [1.0; 4];
+        }
+
+        let x = uv.x * self.width as f32 - 0.5;
+        let y = uv.y * self.height as f32 - 0.5;
+        let (fx, fy) = (x - x.floor(), y - y.floor());
+        let clamp_x = |x: f32| (x.max(0.0) as usize).min(self.width - 1);
+        let clamp_y = |y: f32| (y.max(0.0) as usize).min(self.height - 1);
+        let (x0, x1) = (clamp_x(x.floor()), clamp_x(x.floor() + 1.0));
+        let (y0, y1) = (clamp_y(y.floor()), clamp_y(y.floor() + 1.0));
+
+        let texel = |x: usize, y: usize| {
+            let offset = (y * self.width + x) * self.channels;
+            let texel = &self.pixels[offset..offset + self.channels];
+            match *texel {
+                [alpha] => [1.0, 1.0, 1.0, alpha as f32 / 255.0],
+                [r, g, b, a] => [r, g, b, a].map(|c| c as f32 / 255.0),
+                _ => [1.0; 4],
+            }
+        };
+
+        let (t00, t10, t01, t11) = (texel(x0, y0), texel(x1, y0), texel(x0, y1), texel(x1, y1));
+        std::array::from_fn(|i| {
+            let top = t00[i] + (t10[i] - t00[i]) * fx;
+            let bottom = t01[i] + (t11[i] - t01[i]) * fx;
+            top + (bottom - top) * fy
+        })
+    }
+}
+
+/// Renders the UI into an in-memory RGBA8 buffer, one UI unit per pixel.
+pub struct SoftwareRenderer {
+    target: Target,
+    // Converted user textures, keyed by texture resource.
+    textures: TextureCache<TextureCacheEntry>,
+    stats: RenderStats,
+}
+
+impl SoftwareRenderer {
+    /// Create a renderer with a `width`×`height` buffer cleared to transparent black.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            target: Target::new(width as usize, height as usize),
+            textures: ResourceCache::new(),
+            stats: RenderStats::default(),
+        }
+    }
+
+    /// Resize the buffer, its content is cleared to transparent black.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.target = Target::new(width as usize, height as usize);
+    }
+
+    /// Size of the buffer in pixels.
+    pub fn size(&self) -> (u32, u32) {
+        (self.target.width as u32, self.target.height as u32)
+    }
+
+    /// Rendered pixels, straight alpha RGBA8 rows from the top-left corner.
+    pub fn pixels(&self) -> &[u8] {
+        &self.target.pixels
+    }
+
+    /// Fill the whole buffer with `color`.
+    pub fn clear(&mut self, color: Color) {
+        for pixel in self.target.pixels.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&[color.r, color.g, color.b, color.a]);
+        }
+    }
+
+    /// Statistics of the last rendered frame, each batch counts as one draw call.
+    pub fn stats(&self) -> RenderStats {
+        self.stats
+    }
+
+    /// Rasterize Fyrox-UI `DrawingContext` over the current content of the buffer.
+    pub fn render(&mut self, drawing: &DrawingContext) {
+        self.update_textures(drawing);
+
+        let mut stats = RenderStats {
+            commands: drawing.get_commands().len(),
+            draw_calls: 0,
+        };
+        for (cmd_ui, triangles) in batches(drawing.get_commands()) {
+            stats.draw_calls += 1;
+            let Some(clip) = self.target.clip_rect(&cmd_ui.clip_bounds) else {
+                continue;
+            };
+
+            let masked = match &cmd_ui.clipping_geometry {
+                Some(geometry) => {
+                    self.target
+                        .mask_geometry(&geometry.vertex_buffer, &geometry.triangle_buffer, &clip);
+                    true
+                }
+                None => false,
+            };
+
+            match &cmd_ui.texture {
+                CommandTexture::Font {
+                    font,
+                    page_index,
+                    height,
+                } => {
+                    let state = font.state();
+                    let page = state.data_ref().and_then(|font| {
+                        Some(Image {
+                            width: font.page_size(),
+                            height: font.page_size(),
+                            channels: 1,
+                            pixels: &font.atlases.get(height)?.pages.get(*page_index)?.pixels,
+                        })
+                    });
+                    self.target
+                        .draw(drawing, cmd_ui, triangles, &clip, masked, page.as_ref());
+                }
+                CommandTexture::Texture(resource) => {
+                    let image = self.textures.texture(resource).map(|entry| Image {
+                        width: entry.width,
+                        height: entry.height,
+                        channels: 4,
+                        pixels: &entry.pixels,
+                    });
+                    self.target
+                        .draw(drawing, cmd_ui, triangles, &clip, masked, image.as_ref());
+                }
+                CommandTexture::None => self.target.draw(drawing, cmd_ui, triangles, &clip, masked, None),
+            }
+        }
+        self.stats = stats;
+    }
+
+    /// Convert the user textures referenced by `drawing` that are new or were modified since the
+    /// last frame, and drop the ones that are no longer used by the UI.
+    fn update_textures(&mut self, drawing: &DrawingContext) {
+        self.textures.evict_unused();
+
+        for cmd_ui in drawing.get_commands() {
+            let CommandTexture::Texture(resource) = &cmd_ui.texture else {
+                continue;
+            };
+
+            let Ok(()) = self.textures.update(resource, |data, width, height, _| {
+                let pixels = rgba_pixels(data.pixel_kind(), data.mip_level_data(0));
+                Ok::<_, Infallible>(pixels.map(|pixels| TextureCacheEntry {
+                    width: width as usize,
+                    height: height as usize,
+                    pixels: pixels.into_owned(),
+                }))
+            });
+        }
+    }
+}
+
+/// Color buffer and clip mask the UI is rasterized into.
+struct Target {
+    width: usize,
+    height: usize,
+    // Straight alpha RGBA8, row-major from the top-left corner.
+    pixels: Vec<u8>,
+    // Coverage of the clipping geometry of the current command.
+    mask: Vec<bool>,
+}
+
+impl Target {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width * height * 4],
+            mask: vec![false; width * height],
+        }
+    }
+
+    /// Pixel rectangle covered by `clip_bounds` and the buffer, `None` if empty.
+    fn clip_rect(&self, clip_bounds: &Rect<f32>) -> Option<Rect<usize>> {
+        let left = clip_bounds.position.x.floor();
+        let top = clip_bounds.position.y.floor();
+        let x0 = left.max(0.0) as usize;
+        let y0 = top.max(0.0) as usize;
+        let x1 = ((left + clip_bounds.size.x.ceil()).max(0.0) as usize).min(self.width);
+        let y1 = ((top + clip_bounds.size.y.ceil()).max(0.0) as usize).min(self.height);
+        (x0 < x1 && y0 < y1).then(|| Rect::new(x0, y0, x1 - x0, y1 - y0))
+    }
+
+    /// Replace the clip mask with the coverage of the given clipping geometry.
+    fn mask_geometry(&mut self, vertices: &[Vertex], triangles: &[TriangleDefinition], clip: &Rect<usize>) {
+        self.mask.fill(false);
+        for triangle in triangles {
+            let [a, b, c] = triangle.0.map(|i| vertices[i as usize].pos);
+            rasterize(a, b, c, clip, |x, y, _| self.mask[y * self.width + x] = true);
+        }
+    }
+
+    fn draw(
+        &mut self,
+        drawing: &DrawingContext,
+        cmd_ui: &Command,
+        triangles: Range<usize>,
+        clip: &Rect<usize>,
+        masked: bool,
+        image: Option<&Image>,
+    ) {
+        let vertices = drawing.get_vertices();
+        for triangle in &drawing.get_triangles()[triangles] {
+            let [a, b, c] = triangle.0.map(|i| &vertices[i as usize]);
+            rasterize(a.pos, b.pos, c.pos, clip, |x, y, [wa, wb, wc]| {
+                if masked && !self.mask[y * self.width + x] {
+                    return;
+                }
+
+                let interpolate = |f: fn(&Vertex) -> [f32; 4]| -> [f32; 4] {
+                    let (fa, fb, fc) = (f(a), f(b), f(c));
+                    std::array::from_fn(|i| fa[i] * wa + fb[i] * wb + fc[i] * wc)
+                };
+                let uv = interpolate(|v| [v.tex_coord.x, v.tex_coord.y, 0.0, 0.0]);
+                let vertex_color = interpolate(|v| color_to_array(v.color));
+
+                // Same steps as `ui.frag`: brush, texture or font alpha, opacity, vertex color.
+                let fragment = Vector2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let mut color = brush_color(&cmd_ui.brush, &cmd_ui.bounds, fragment);
+                if let Some(image) = image {
+                    let texel = image.sample(Vector2::new(uv[0], uv[1]));
+                    color = std::array::from_fn(|i| color[i] * texel[i]);
+                }
+                color[3] *= cmd_ui.opacity;
+                let color: [f32; 4] = std::array::from_fn(|i| color[i] * vertex_color[i]);
+
+                self.blend(x, y, color);
+            });
+        }
+    }
+
+    /// Blend `src` over the pixel like the GPU pipeline: straight alpha for color, "over" for
+    /// alpha.
+    fn blend(&mut self, x: usize, y: usize, src: [f32; 4]) {
+        let offset = (y * self.width + x) * 4;
+        let dst = &mut self.pixels[offset..offset + 4];
+        let alpha = src[3].clamp(0.0, 1.0);
+        for i in 0..3 {
+            let value = src[i] * alpha + dst[i] as f32 / 255.0 * (1.0 - alpha);
+            dst[i] = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        }
+        let value = alpha + dst[3] as f32 / 255.0 * (1.0 - alpha);
+        dst[3] = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    }
+}
+
+/// Call `shade` with the barycentric weights of every pixel of `clip` whose center is covered by
+/// the triangle `a`, `b`, `c`.
+///
+/// Uses the top-left fill rule, so pixels on edges shared by adjacent triangles are shaded once.
+fn rasterize(
+    a: Vector2<f32>,
+    b: Vector2<f32>,
+    c: Vector2<f32>,
+    clip: &Rect<usize>,
+    mut shade: impl FnMut(usize, usize, [f32; 3]),
+) {
+    let edge =
+        |p: Vector2<f32>, q: Vector2<f32>, r: Vector2<f32>| (q.x - p.x) * (r.y - p.y) - (q.y - p.y) * (r.x - p.x);
+
+    // Make the winding clockwise on screen so all edge functions are positive inside.
+    let (a, b, c) = if edge(a, b, c) < 0.0 { (a, c, b) } else { (a, b, c) };
+    let area = edge(a, b, c);
+    if area <= 0.0 {
+        return;
+    }
+
+    // With that winding, left edges go up and top edges go right.
+    let top_left = |p: Vector2<f32>, q: Vector2<f32>| q.y < p.y || (q.y == p.y && q.x > p.x);
+    let inside = |w: f32, p: Vector2<f32>, q: Vector2<f32>| w > 0.0 || (w == 0.0 && top_left(p, q));
+
+    let x0 = (a.x.min(b.x).min(c.x).floor().max(0.0) as usize).max(clip.position.x);
+    let y0 = (a.y.min(b.y).min(c.y).floor().max(0.0) as usize).max(clip.position.y);
+    let x1 = (a.x.max(b.x).max(c.x).ceil().max(0.0) as usize).min(clip.position.x + clip.size.x);
+    let y1 = (a.y.max(b.y).max(c.y).ceil().max(0.0) as usize).min(clip.position.y + clip.size.y);
+
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let p = Vector2::new(x as f32 + 0.5, y as f32 + 0.5);
+            let (wa, wb, wc) = (edge(b, c, p), edge(c, a, p), edge(a, b, p));
+            if inside(wa, b, c) && inside(wb, c, a) && inside(wc, a, b) {
+                shade(x, y, [wa / area, wb / area, wc / area]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fyrox_ui::brush::{Brush, GradientPoint};
+    use fyrox_ui::draw::{ClippingGeometry, Draw};
+    use fyrox_ui::style::DEFAULT_STYLE;
+
+    /// Number of times each pixel of a `width`×`height` clip is shaded by the given triangles.
+    fn coverage(triangles: &[[(f32, f32); 3]], clip: Rect<usize>) -> Vec<u32> {
+        let width = clip.position.x + clip.size.x;
+        let mut counts = vec![0; width * (clip.position.y + clip.size.y)];
+        for &[a, b, c] in triangles {
+            let [a, b, c] = [a, b, c].map(|(x, y)| Vector2::new(x, y));
+            rasterize(a, b, c, &clip, |x, y, _| counts[y * width + x] += 1);
+        }
+        counts
+    }
+
+    fn assert_color_eq(actual: [f32; 4], expected: [f32; 4]) {
+        let close = actual.iter().zip(expected).all(|(a, e)| (a - e).abs() < 1e-4);
+        assert!(close, "expected {expected:?}, got {actual:?}");
+    }
+
+    #[test]
+    fn shared_edges_are_shaded_once() {
+        // Both triangles share the diagonal, which goes through pixel centers.
+        let counts = coverage(
+            &[
+                [(0.0, 0.0), (4.0, 0.0), (4.0, 4.0)],
+                [(0.0, 0.0), (4.0, 4.0), (0.0, 4.0)],
+            ],
+            Rect::new(0, 0, 4, 4),
+        );
+        assert_eq!(counts, vec![1; 16]);
+    }
+
+    #[test]
+    fn top_left_edges_own_the_pixel_centers_on_them() {
+        // Every edge of the square goes through pixel centers, only the left and top ones are in.
+        let counts = coverage(
+            &[
+                [(0.5, 0.5), (2.5, 0.5), (2.5, 2.5)],
+                [(0.5, 0.5), (2.5, 2.5), (0.5, 2.5)],
+            ],
+            Rect::new(0, 0, 3, 3),
+        );
+        #[rustfmt::skip]
+        assert_eq!(counts, vec![
+            1, 1, 0,
+            1, 1, 0,
+            0, 0, 0,
+        ]);
+    }
+
+    #[test]
+    fn winding_does_not_matter() {
+        let clockwise = coverage(&[[(0.0, 0.0), (3.0, 0.0), (0.0, 3.0)]], Rect::new(0, 0, 3, 3));
+        let counter_clockwise = coverage(&[[(0.0, 0.0), (0.0, 3.0), (3.0, 0.0)]], Rect::new(0, 0, 3, 3));
+        assert_eq!(clockwise, counter_clockwise);
+        // Pixel centers on the hypotenuse are on a bottom-right edge, so they are out.
+        #[rustfmt::skip]
+        assert_eq!(clockwise, vec![
+            1, 1, 0,
+            1, 0, 0,
+            0, 0, 0,
+        ]);
+    }
+
+    #[test]
+    fn degenerate_triangles_are_skipped() {
+        let counts = coverage(&[[(0.0, 0.0), (2.0, 2.0), (4.0, 4.0)]], Rect::new(0, 0, 4, 4));
+        assert!(counts.iter().all(|&count| count == 0));
+    }
+
+    #[test]
+    fn rasterization_stays_in_the_clip() {
+        let counts = coverage(
+            &[
+                [(-10.0, -10.0), (10.0, -10.0), (10.0, 10.0)],
+                [(-10.0, -10.0), (10.0, 10.0), (-10.0, 10.0)],
+            ],
+            Rect::new(1, 1, 2, 2),
+        );
+        #[rustfmt::skip]
+        assert_eq!(counts, vec![
+            0, 0, 0,
+            0, 1, 1,
+            0, 1, 1,
+        ]);
+    }
+
+    #[test]
+    fn clip_rect_covers_partial_pixels_and_stays_in_the_buffer() {
+        let target = Target::new(10, 8);
+        assert_eq!(
+            target.clip_rect(&Rect::new(2.2, 3.7, 3.1, 1.0)),
+            Some(Rect::new(2, 3, 4, 1))
+        );
+        assert_eq!(
+            target.clip_rect(&Rect::new(-2.5, 1.2, 5.0, 20.0)),
+            Some(Rect::new(0, 1, 2, 7))
+        );
+        assert_eq!(target.clip_rect(&Rect::new(12.0, 0.0, 5.0, 5.0)), None);
+        assert_eq!(target.clip_rect(&Rect::new(1.0, 1.0, 0.0, 5.0)), None);
+    }
+
+    #[test]
+    fn blending_is_straight_alpha_over() {
+        let mut target = Target::new(3, 1);
+        target
+            .pixels
+            .copy_from_slice(&[0, 0, 0, 255, 0, 0, 0, 0, 10, 20, 30, 40]);
+
+        target.blend(0, 0, [1.0, 0.0, 0.0, 0.5]);
+        target.blend(1, 0, [1.0, 1.0, 1.0, 0.5]);
+        // Alpha above one, e.g. from an opacity overshoot, replaces the pixel.
+        target.blend(2, 0, [0.0, 1.0, 0.0, 2.0]);
+
+        assert_eq!(target.pixels, [128, 0, 0, 255, 128, 128, 128, 128, 0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn clipping_geometry_masks_commands() {
+        let mut geometry = ClippingGeometry {
+            vertex_buffer: Vec::new(),
+            triangle_buffer: Vec::new(),
+            transform_stack: Default::default(),
+        };
+        geometry.push_triangle_filled([Vector2::new(0.0, 0.0), Vector2::new(4.0, 0.0), Vector2::new(0.0, 4.0)]);
+
+        let mut drawing = DrawingContext::new(DEFAULT_STYLE.resource.clone());
+        drawing.push_rect_filled(&Rect::new(0.0, 0.0, 4.0, 4.0), None);
+        drawing.commit(
+            Rect::new(0.0, 0.0, 4.0, 4.0),
+            Brush::Solid(Color::RED),
+            CommandTexture::None,
+            Some(geometry),
+        );
+        // The mask is rebuilt for every clipped command, so this one draws the whole rectangle.
+        drawing.push_rect_filled(&Rect::new(0.0, 3.0, 4.0, 1.0), None);
+        drawing.commit(
+            Rect::new(0.0, 0.0, 4.0, 4.0),
+            Brush::Solid(Color::GREEN),
+            CommandTexture::None,
+            None,
+        );
+
+        let mut renderer = SoftwareRenderer::new(4, 4);
+        renderer.render(&drawing);
+
+        let red = [255, 0, 0, 255];
+        let green = [0, 255, 0, 255];
+        let none = [0, 0, 0, 0];
+        #[rustfmt::skip]
+        let expected = [
+            red, red, red, none,
+            red, red, none, none,
+            red, none, none, none,
+            green, green, green, green,
+        ];
+        assert_eq!(renderer.pixels(), expected.as_flattened());
+        assert_eq!(renderer.stats().draw_calls, 2);
+    }
+
+    #[test]
+    fn textures_are_sampled_bilinearly_with_clamped_edges() {
+        let image = Image {
+            width: 2,
+            height: 1,
+            channels: 4,
+            pixels: &[0, 0, 0, 255, 255, 255, 255, 255],
+        };
+
+        // Texel centers are at 0.25 and 0.75.
+        assert_color_eq(image.sample(Vector2::new(0.25, 0.5)), [0.0, 0.0, 0.0, 1.0]);
+        assert_color_eq(image.sample(Vector2::new(0.5, 0.5)), [0.5, 0.5, 0.5, 1.0]);
+        assert_color_eq(image.sample(Vector2::new(0.625, 0.0)), [0.75, 0.75, 0.75, 1.0]);
+        assert_color_eq(image.sample(Vector2::new(-1.0, 2.0)), [0.0, 0.0, 0.0, 1.0]);
+        assert_color_eq(image.sample(Vector2::new(1.0, 0.5)), [1.0, 1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn font_pages_are_sampled_as_white_coverage() {
+        let image = Image {
+            width: 1,
+            height: 1,
+            channels: 1,
+            pixels: &[51],
+        };
+        assert_color_eq(image.sample(Vector2::new(0.5, 0.5)), [1.0, 1.0, 1.0, 0.2]);
+
+        let empty = Image {
+            width: 0,
+            height: 0,
+            channels: 4,
+            pixels: &[],
+        };
+        assert_color_eq(empty.sample(Vector2::new(0.5, 0.5)), [1.0; 4]);
+    }
+
+    #[test]
+    fn brush_color_matches_the_shader() {
+        let point = |stop, color| GradientPoint { stop, color };
+        let bounds = Rect::new(10.0, 20.0, 100.0, 50.0);
+        // Fragment at the given position in the local [0; 1] space of `bounds`.
+        let at = |x: f32, y: f32| Vector2::new(10.0 + x * 100.0, 20.0 + y * 50.0);
+        let horizontal = |stops| Brush::LinearGradient {
+            from: Vector2::new(0.0, 0.0),
+            to: Vector2::new(1.0, 0.0),
+            stops,
+        };
+
+        let cases = [
+            // Solid brushes ignore the position.
+            (
+                Brush::Solid(Color::from_rgba(255, 0, 0, 51)),
+                at(0.3, 0.3),
+                [1.0, 0.0, 0.0, 0.2],
+            ),
+            // Linear gradients project on their axis, other coordinates don't matter.
+            (
+                horizontal(vec![point(0.0, Color::BLACK), point(1.0, Color::WHITE)]),
+                at(0.25, 0.9),
+                [0.25, 0.25, 0.25, 1.0],
+            ),
+            // The projection is clamped to the ends of the axis.
+            (
+                horizontal(vec![point(0.0, Color::BLACK), point(1.0, Color::WHITE)]),
+                at(3.0, 0.0),
+                [1.0; 4],
+            ),
+            // Before the first stop, the first color.
+            (
+                horizontal(vec![point(0.5, Color::RED), point(1.0, Color::BLUE)]),
+                at(0.25, 0.0),
+                [1.0, 0.0, 0.0, 1.0],
+            ),
+            // After the last stop, the last color.
+            (
+                horizontal(vec![point(0.0, Color::RED), point(0.5, Color::BLUE)]),
+                at(0.75, 0.0),
+                [0.0, 0.0, 1.0, 1.0],
+            ),
+            // Between two stops, interpolated between their colors only.
+            (
+                horizontal(vec![
+                    point(0.0, Color::RED),
+                    point(0.5, Color::GREEN),
+                    point(1.0, Color::BLUE),
+                ]),
+                at(0.625, 0.0),
+                [0.0, 0.75, 0.25, 1.0],
+            ),
+            // Radial gradients use the distance to the center in local space.
+            (
+                Brush::RadialGradient {
+                    center: Vector2::new(0.5, 0.5),
+                    stops: vec![point(0.0, Color::WHITE), point(1.0, Color::BLACK)],
+                },
+                at(0.5, 1.0),
+                [0.5, 0.5, 0.5, 1.0],
+            ),
+            // Without points the shader falls back to the solid color, which is white.
+            (horizontal(vec![]), at(0.5, 0.5), [1.0; 4]),
+        ];
+
+        for (brush, position, expected) in cases {
+            assert_color_eq(brush_color(&brush, &bounds, position), expected);
+        }
+    }
+}
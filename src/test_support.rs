@@ -0,0 +1,42 @@
+//! Shared setup of the tests using SDL.
+
+use sdl3::gpu::{Device, ShaderFormat};
+use sdl3::{Sdl, VideoSubsystem};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// Lock taken by the tests using SDL, whose video subsystem must not be used from several
+/// threads at once.
+pub(crate) static SDL_TEST_LOCK: Mutex<()> = Mutex::new(());
+
+/// GPU device of the tests rendering through SDL, on the offscreen video driver unless
+/// `SDL_VIDEO_DRIVER` picks another one.
+///
+/// These tests are `#[ignore]`d, as CI runners usually have no GPU driver SDL supports, and run
+/// with `cargo test -- --ignored` on a machine with one, e.g. with lavapipe. Without a device they
+/// fail rather than pass untested.
+pub(crate) struct TestGpu {
+    pub(crate) device: Device,
+    pub(crate) video: VideoSubsystem,
+    _sdl: Sdl,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl TestGpu {
+    pub(crate) fn new() -> Self {
+        let lock = SDL_TEST_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        if std::env::var_os("SDL_VIDEO_DRIVER").is_none() {
+            sdl3::hint::set("SDL_VIDEO_DRIVER", "offscreen");
+        }
+        let sdl = sdl3::init().expect("SDL initializes");
+        let video = sdl.video().expect("SDL has a video driver");
+        let device =
+            Device::new(ShaderFormat::SPIRV, false).expect("no GPU device, these tests need a GPU driver SDL supports");
+
+        Self {
+            device,
+            video,
+            _sdl: sdl,
+            _lock: lock,
+        }
+    }
+}
@@ -1,64 +1,75 @@
-use std::error::Error;
+use std::borrow::Cow;
 
+use fyrox_ui::brush::Brush;
+use fyrox_ui::core::algebra::Vector2;
+use fyrox_ui::core::color::Color;
+use fyrox_ui::core::math::Rect;
+use fyrox_ui::texture::TexturePixelKind;
 use sdl3::gpu::{Device, TextureCreateInfo, TextureFormat, TextureType, TextureUsage, *};
 
-pub fn create_buffer_with_data<T: Copy>(
+/// Make sure `buffer` can hold `size` bytes, replacing it with a bigger one if it cannot.
+///
+/// Buffers grow to the next power of two, so slowly growing data does not reallocate every frame.
+pub fn ensure_buffer<'a>(
     device: &Device,
-    transfer_buffer: &TransferBuffer,
-    copy_pass: &CopyPass,
+    buffer: &'a mut Option<Buffer>,
     usage: BufferUsageFlags,
-    data: &[T],
-) -> Result<Buffer, sdl3::Error> {
-    // Figure out the length of the data in bytes
-    let len_bytes = std::mem::size_of_val(data);
-
-    // Create the buffer with the size and usage we want
-    let buffer = device
-        .create_buffer()
-        .with_size(len_bytes as u32)
-        .with_usage(usage)
-        .build()?;
-
-    // Map the transfer buffer's memory into a place we can copy into, and copy the data
-    //
-    // Note: We set `cycle` to true since we're reusing the same transfer buffer to
-    // initialize both the vertex and index buffer. This makes SDL synchronize the transfers
-    // so that one doesn't interfere with the other.
-    let mut map = transfer_buffer.map::<T>(device, true);
-    let mem = map.mem_mut();
-    for (index, &value) in data.iter().enumerate() {
-        mem[index] = value;
+    size: u32,
+) -> Result<&'a Buffer, sdl3::Error> {
+    if buffer.as_ref().is_none_or(|buffer| buffer.len() < size) {
+        *buffer = Some(
+            device
+                .create_buffer()
+                .with_size(size.max(1).next_power_of_two())
+                .with_usage(usage)
+                .build()?,
+        );
     }
 
-    // Now unmap the memory since we're done copying
-    map.unmap();
-
-    // Finally, add a command to the copy pass to upload this data to the GPU
-    //
-    // Note: We also set `cycle` to true here for the same reason.
-    copy_pass.upload_to_gpu_buffer(
-        TransferBufferLocation::new()
-            .with_offset(0)
-            .with_transfer_buffer(transfer_buffer),
-        BufferRegion::new()
-            .with_offset(0)
-            .with_size(len_bytes as u32)
-            .with_buffer(&buffer),
-        true,
-    );
+    Ok(buffer.as_ref().unwrap())
+}
+
+/// Make sure the upload `transfer_buffer` can hold `size` bytes, see [`ensure_buffer`].
+pub fn ensure_transfer_buffer<'a>(
+    device: &Device,
+    transfer_buffer: &'a mut Option<TransferBuffer>,
+    size: u32,
+) -> Result<&'a TransferBuffer, sdl3::Error> {
+    if transfer_buffer.as_ref().is_none_or(|buffer| buffer.len() < size) {
+        *transfer_buffer = Some(
+            device
+                .create_transfer_buffer()
+                .with_size(size.max(1).next_power_of_two())
+                .with_usage(TransferBufferUsage::UPLOAD)
+                .build()?,
+        );
+    }
 
-    Ok(buffer)
+    Ok(transfer_buffer.as_ref().unwrap())
 }
 
+/// View a slice of plain data as raw bytes, e.g. to copy it into a mapped transfer buffer.
+///
+/// # Safety
+///
+/// `T` must not contain padding bytes, which are uninitialized. Fyrox vertices and integer
+/// indices are fine.
+pub(crate) unsafe fn as_bytes<T: Copy>(data: &[T]) -> &[u8] {
+    // SAFETY: the slice is valid for `size_of_val(data)` bytes, and the caller guarantees `T` has
+    // no uninitialized padding.
+    unsafe { std::slice::from_raw_parts(data.as_ptr() as *const u8, std::mem::size_of_val(data)) }
+}
+
+/// Create an A8 texture with the given coverage, uploaded through `transfer_buffer`, see
+/// [`update_texture_level`].
 pub fn create_texture(
     device: &Device,
     copy_pass: &CopyPass,
+    transfer_buffer: &mut Option<TransferBuffer>,
     image_data: &[u8],
     width: u32,
     height: u32,
-) -> Result<Texture<'static>, Box<dyn Error>> {
-    let size_bytes = width * height;
-
+) -> Result<Texture<'static>, sdl3::Error> {
     let texture = device.create_texture(
         TextureCreateInfo::new()
             .with_format(TextureFormat::A8Unorm)
@@ -70,28 +81,253 @@ pub fn create_texture(
             .with_usage(TextureUsage::SAMPLER),
     )?;
 
-    let transfer_buffer = device
-        .create_transfer_buffer()
-        .with_size(size_bytes)
-        .with_usage(TransferBufferUsage::UPLOAD)
-        .build()?;
+    update_texture(
+        device,
+        copy_pass,
+        transfer_buffer,
+        &texture,
+        image_data,
+        width,
+        height,
+        false,
+    )?;
+
+    Ok(texture)
+}
+
+/// Create an empty sampled 2D texture with the given format and number of mip levels.
+pub fn create_texture_with_format(
+    device: &Device,
+    format: TextureFormat,
+    width: u32,
+    height: u32,
+    num_levels: u32,
+) -> Result<Texture<'static>, sdl3::Error> {
+    let texture = device.create_texture(
+        TextureCreateInfo::new()
+            .with_format(format)
+            .with_type(TextureType::_2D)
+            .with_width(width)
+            .with_height(height)
+            .with_layer_count_or_depth(1)
+            .with_num_levels(num_levels)
+            .with_usage(TextureUsage::SAMPLER),
+    )?;
+
+    Ok(texture)
+}
+
+/// Create a multisample color target, only meant to be resolved into a single-sample texture.
+pub fn create_multisample_target(
+    device: &Device,
+    format: TextureFormat,
+    width: u32,
+    height: u32,
+    sample_count: SampleCount,
+) -> Result<Texture<'static>, sdl3::Error> {
+    device.create_texture(
+        TextureCreateInfo::new()
+            .with_format(format)
+            .with_type(TextureType::_2D)
+            .with_width(width)
+            .with_height(height)
+            .with_layer_count_or_depth(1)
+            .with_num_levels(1)
+            .with_sample_count(sample_count)
+            .with_usage(TextureUsage::COLOR_TARGET),
+    )
+}
+
+/// Create a single sample color target that is sampled afterwards, e.g. to composite it over
+/// another target.
+pub fn create_layer_target(
+    device: &Device,
+    format: TextureFormat,
+    width: u32,
+    height: u32,
+) -> Result<Texture<'static>, sdl3::Error> {
+    device.create_texture(
+        TextureCreateInfo::new()
+            .with_format(format)
+            .with_type(TextureType::_2D)
+            .with_width(width)
+            .with_height(height)
+            .with_layer_count_or_depth(1)
+            .with_num_levels(1)
+            .with_usage(TextureUsage::COLOR_TARGET | TextureUsage::SAMPLER),
+    )
+}
 
-    let mut buffer_mem = transfer_buffer.map::<u8>(device, false);
-    buffer_mem.mem_mut().copy_from_slice(image_data);
+/// Upload new pixels into the first mip level of an existing texture.
+///
+/// With `cycle` set, SDL swaps in a fresh backing texture if the current one is still in use by
+/// the GPU, so the texture can be safely rewritten every frame.
+#[allow(clippy::too_many_arguments)]
+pub fn update_texture(
+    device: &Device,
+    copy_pass: &CopyPass,
+    transfer_buffer: &mut Option<TransferBuffer>,
+    texture: &Texture,
+    image_data: &[u8],
+    width: u32,
+    height: u32,
+    cycle: bool,
+) -> Result<(), sdl3::Error> {
+    update_texture_level(
+        device,
+        copy_pass,
+        transfer_buffer,
+        texture,
+        0,
+        image_data,
+        width,
+        height,
+        cycle,
+    )
+}
+
+/// Upload new pixels into one mip level of an existing texture.
+///
+/// `image_data` must be tightly packed and match the texture format and the size of the level.
+/// It is staged in `transfer_buffer`, grown by [`ensure_transfer_buffer`] and shared with the
+/// geometry uploads. The buffer is cycled when mapped, so uploads recorded earlier in the frame
+/// keep their data and SDL reuses the backing memory once the GPU is done with it.
+#[allow(clippy::too_many_arguments)]
+pub fn update_texture_level(
+    device: &Device,
+    copy_pass: &CopyPass,
+    transfer_buffer: &mut Option<TransferBuffer>,
+    texture: &Texture,
+    mip_level: u32,
+    image_data: &[u8],
+    width: u32,
+    height: u32,
+    cycle: bool,
+) -> Result<(), sdl3::Error> {
+    let transfer_buffer = ensure_transfer_buffer(device, transfer_buffer, image_data.len() as u32)?;
+
+    let mut buffer_mem = transfer_buffer.map::<u8>(device, true);
+    buffer_mem.mem_mut()[..image_data.len()].copy_from_slice(image_data);
     buffer_mem.unmap();
 
     copy_pass.upload_to_gpu_texture(
         TextureTransferInfo::new()
-            .with_transfer_buffer(&transfer_buffer)
+            .with_transfer_buffer(transfer_buffer)
             .with_offset(0),
         TextureRegion::new()
-            .with_texture(&texture)
+            .with_texture(texture)
+            .with_mip_level(mip_level)
             .with_layer(0)
             .with_width(width)
             .with_height(height)
             .with_depth(1),
-        false,
+        cycle,
     );
 
-    Ok(texture)
+    Ok(())
+}
+
+/// Pick the SDL GPU format used to sample a fyrox pixel kind.
+///
+/// Fyrox textures carry no color space, their 8-bit colors are sRGB encoded like the images they
+/// are loaded from. They get the sRGB variant of their format, so the GPU decodes them to linear
+/// values when sampling. Returns `None` for pixel kinds the UI renderer does not support.
+pub fn texture_format(pixel_kind: TexturePixelKind) -> Option<TextureFormat> {
+    Some(match pixel_kind {
+        TexturePixelKind::RGBA8
+        | TexturePixelKind::RGB8
+        | TexturePixelKind::BGR8
+        | TexturePixelKind::Luminance8
+        | TexturePixelKind::LuminanceAlpha8 => TextureFormat::R8g8b8a8UnormSrgb,
+        TexturePixelKind::BGRA8 => TextureFormat::B8g8r8a8UnormSrgb,
+        // Data channels rather than colors, SDL has no sRGB variant of these anyway.
+        TexturePixelKind::R8 => TextureFormat::R8Unorm,
+        TexturePixelKind::RG8 => TextureFormat::R8g8Unorm,
+        _ => return None,
+    })
+}
+
+/// Returns `true` if the GPU reads and writes linear values from textures of `format`: sRGB
+/// formats are decoded when sampled and encoded when rendered to, float formats are stored as is.
+pub fn is_linear_format(format: TextureFormat) -> bool {
+    matches!(
+        format,
+        TextureFormat::R8g8b8a8UnormSrgb
+            | TextureFormat::B8g8r8a8UnormSrgb
+            | TextureFormat::R16g16b16a16Float
+            | TextureFormat::R32g32b32a32Float
+            | TextureFormat::R11g11b10Ufloat
+    )
+}
+
+/// Convert fyrox pixels to the layout of the format picked by [`texture_format`].
+///
+/// SDL has no 3-channel or luminance formats, so those are expanded to RGBA.
+pub fn convert_texture_pixels(pixel_kind: TexturePixelKind, data: &[u8]) -> Cow<'_, [u8]> {
+    match pixel_kind {
+        TexturePixelKind::RGB8 => data.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        TexturePixelKind::BGR8 => data.chunks_exact(3).flat_map(|p| [p[2], p[1], p[0], 255]).collect(),
+        TexturePixelKind::Luminance8 => data.iter().flat_map(|&l| [l, l, l, 255]).collect(),
+        TexturePixelKind::LuminanceAlpha8 => data.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        _ => Cow::Borrowed(data),
+    }
+}
+
+/// Convert fyrox pixels of any supported kind to RGBA, with the channels the GPU formats picked by
+/// [`texture_format`] are sampled with.
+pub fn rgba_pixels(pixel_kind: TexturePixelKind, data: &[u8]) -> Option<Cow<'_, [u8]>> {
+    Some(match pixel_kind {
+        TexturePixelKind::RGBA8
+        | TexturePixelKind::RGB8
+        | TexturePixelKind::BGR8
+        | TexturePixelKind::Luminance8
+        | TexturePixelKind::LuminanceAlpha8 => convert_texture_pixels(pixel_kind, data),
+        TexturePixelKind::BGRA8 => data.chunks_exact(4).flat_map(|p| [p[2], p[1], p[0], p[3]]).collect(),
+        TexturePixelKind::R8 => data.iter().flat_map(|&r| [r, 0, 0, 255]).collect(),
+        TexturePixelKind::RG8 => data.chunks_exact(2).flat_map(|p| [p[0], p[1], 0, 255]).collect(),
+        _ => return None,
+    })
+}
+
+/// Convert a fyrox color to normalized RGBA.
+pub fn color_to_array(c: Color) -> [f32; 4] {
+    [
+        c.r as f32 / 255.0,
+        c.g as f32 / 255.0,
+        c.b as f32 / 255.0,
+        c.a as f32 / 255.0,
+    ]
+}
+
+/// Evaluate `brush` at `position`, the CPU counterpart of `brushColor` in `ui.frag`.
+///
+/// Gradient points are given in the local [0; 1] space of `bounds`. Returns normalized RGBA.
+pub fn brush_color(brush: &Brush, bounds: &Rect<f32>, position: Vector2<f32>) -> [f32; 4] {
+    let size = Vector2::new(bounds.size.x.max(1e-6), bounds.size.y.max(1e-6));
+    let local = (position - bounds.position).component_div(&size);
+
+    let (stops, t) = match brush {
+        Brush::Solid(color) => return color_to_array(*color),
+        Brush::LinearGradient { from, to, stops } => {
+            let ab = to - from;
+            let t = (local - from).dot(&ab) / ab.dot(&ab).max(1e-6);
+            (stops, t.clamp(0.0, 1.0))
+        }
+        Brush::RadialGradient { center, stops } => (stops, (local - center).norm().clamp(0.0, 1.0)),
+    };
+
+    let Some(last) = stops.len().checked_sub(1) else {
+        return [1.0; 4];
+    };
+    let current = stops.iter().rposition(|point| t > point.stop).unwrap_or(0);
+    let next = (current + 1).min(last);
+
+    let delta = stops[next].stop - stops[current].stop;
+    let mix = if delta > 0.0 {
+        ((t - stops[current].stop) / delta).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let (a, b) = (color_to_array(stops[current].color), color_to_array(stops[next].color));
+    std::array::from_fn(|i| a[i] + (b[i] - a[i]) * mix)
 }
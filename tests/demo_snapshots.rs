@@ -0,0 +1,51 @@
+//! Golden images of the demo widget gallery, rendered through the GPU renderer.
+//!
+//! The test needs a GPU device, so it is ignored unless run with
+//! `cargo test --features snapshot --test demo_snapshots -- --ignored`. Set `UPDATE_SNAPSHOTS=1`
+//! to record the references after an intended change of look, or run `just bless`.
+
+#[path = "../examples/demo/ui.rs"]
+mod ui;
+
+use fyrox_ui::core::color::Color;
+use fyrox_ui_sdl3::FyroxUiSdl;
+use fyrox_ui_sdl3::renderer::UiRendererConfig;
+use fyrox_ui_sdl3::snapshot::{Headless, Snapshot};
+use sdl3::gpu::{SampleCount, TextureFormat};
+use ui::DemoUi;
+
+const SIZE: (u32, u32) = (800, 600);
+
+// SDL is initialized once per process, so every snapshot is taken from the same test.
+#[test]
+#[ignore = "needs a GPU device, run with --ignored"]
+fn demo() {
+    let headless = Headless::new().unwrap();
+
+    let configs = [
+        ("demo", UiRendererConfig::default()),
+        (
+            "demo_msaa",
+            UiRendererConfig {
+                sample_count: SampleCount::MSAA4x,
+                ..Default::default()
+            },
+        ),
+    ];
+    for (name, config) in configs {
+        let mut fyrox_ui =
+            FyroxUiSdl::with_format(&headless.device, TextureFormat::R8g8b8a8Unorm, SIZE, config).unwrap();
+        DemoUi::build(&mut fyrox_ui.ui);
+
+        Snapshot::new(SIZE.0, SIZE.1)
+            .with_frames(10, 1.0 / 60.0)
+            .with_tolerance(2)
+            .with_background(Color::opaque(128, 128, 128))
+            .compare(
+                &headless.device,
+                &mut fyrox_ui,
+                format!("{}/tests/snapshots/{name}.png", env!("CARGO_MANIFEST_DIR")),
+            )
+            .unwrap_or_else(|err| panic!("{name}: {err}"));
+    }
+}